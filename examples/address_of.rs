@@ -0,0 +1,16 @@
+use std::ptr;
+
+#[repr(packed)]
+struct P {
+    a: u8,
+    b: u32,
+}
+
+fn f(p: &mut P) -> (*const u32, *mut u32) {
+    (ptr::addr_of!(p.b), ptr::addr_of_mut!(p.b))
+}
+
+fn main() {
+    let mut p = P { a: 1, b: 2 };
+    f(&mut p);
+}
@@ -0,0 +1,14 @@
+use std::fmt::Debug;
+
+fn plain(x: &dyn Debug) -> String { format!("{:?}", x) }
+fn send(x: &(dyn Debug + Send)) -> String { format!("{:?}", x) }
+fn send_sync(x: &(dyn Debug + Send + Sync)) -> String { format!("{:?}", x) }
+fn sync_send(x: &(dyn Debug + Sync + Send)) -> String { format!("{:?}", x) }
+
+fn main() {
+    let x = 1_u32;
+    plain(&x);
+    send(&x);
+    send_sync(&x);
+    sync_send(&x);
+}
@@ -0,0 +1,18 @@
+struct Buf<const N: usize = 16> {
+    data: [u8; N],
+}
+
+impl<const N: usize> Buf<N> {
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+}
+
+// `Buf` with the argument omitted uses the default, `Buf<16>`.
+fn default_len(b: &Buf) -> usize {
+    b.len()
+}
+
+fn explicit_len(b: &Buf<4>) -> usize {
+    b.len()
+}
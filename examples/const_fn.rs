@@ -0,0 +1,17 @@
+#![feature(core_intrinsics)]
+
+use std::intrinsics;
+
+// `double` should be marked `is_const`, and the call to `size_of` should resolve to an intrinsic
+// instance named `size_of`.
+const fn double(x: u32) -> u32 {
+    x * 2
+}
+
+fn runtime_size<T>() -> usize {
+    intrinsics::size_of::<T>()
+}
+
+fn call_both(x: u32) -> usize {
+    double(x) as usize + runtime_size::<u64>()
+}
@@ -0,0 +1,17 @@
+#[derive(Clone, Copy)]
+struct Pair {
+    x: u8,
+    y: u16,
+}
+
+const A: &[u16] = &[1, 2, 3];
+const PAIRS: &[Pair] = &[Pair { x: 1, y: 2 }, Pair { x: 3, y: 4 }];
+const S: &str = "hello";
+
+fn f() -> (&'static [u16], &'static [Pair], &'static str) {
+    (A, PAIRS, S)
+}
+
+fn main() {
+    f();
+}
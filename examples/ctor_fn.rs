@@ -0,0 +1,16 @@
+// `Some` and `Pair` are used as function values here, so their `FnDef` types carry a `ctor`
+// record naming the ADT and variant each one builds.
+struct Pair(i32, i32);
+
+fn wrap_all(xs: Vec<u32>) -> Vec<Option<u32>> {
+    xs.into_iter().map(Some).collect()
+}
+
+fn make_pair(f: fn(i32, i32) -> Pair) -> Pair {
+    f(1, 2)
+}
+
+fn use_ctor() -> i32 {
+    let p = make_pair(Pair);
+    p.0 + p.1
+}
@@ -0,0 +1,23 @@
+// Built as an executable, only `main` and the items exported to C are roots.  `unreachable` is
+// never called, so it should not appear in the output.
+
+#[used]
+static KEEP: [u8; 4] = *b"keep";
+
+#[no_mangle]
+pub extern "C" fn called_from_c(x: u32) -> u32 {
+    helper(x) + 1
+}
+
+fn helper(x: u32) -> u32 {
+    x * 2
+}
+
+#[allow(dead_code)]
+fn unreachable() -> u32 {
+    helper(3)
+}
+
+fn main() {
+    println!("{}", helper(1));
+}
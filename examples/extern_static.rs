@@ -0,0 +1,14 @@
+extern "C" {
+    static EXT: u32;
+    static mut EXT_MUT: u32;
+}
+
+static EXT_REF: &'static u32 = unsafe { &EXT };
+
+fn read_ext() -> u32 {
+    unsafe { EXT + EXT_MUT + *EXT_REF }
+}
+
+fn write_ext(x: u32) {
+    unsafe { EXT_MUT = x; }
+}
@@ -0,0 +1,25 @@
+// The bodies of constants and static initializers are taken before borrowck removes `FakeRead`
+// statements, so `let _ = ...` and the `match` below keep theirs.  Normal functions use optimized
+// MIR, which has none.  `Retag` statements appear only under `-Z mir-emit-retag`, on the
+// reference arguments of `swap_refs`.
+
+const PICK: u32 = {
+    let pair = (1, 2);
+    let _ = pair;
+    match pair {
+        (a, _) if a > 0 => a,
+        (_, b) => b,
+    }
+};
+
+static PICKED: u32 = {
+    let x = PICK;
+    let _ = x;
+    x + 1
+};
+
+fn swap_refs(a: &mut u32, b: &mut u32) {
+    let tmp = *a;
+    *a = *b;
+    *b = tmp + PICK + PICKED;
+}
@@ -0,0 +1,29 @@
+#![feature(generators, generator_trait)]
+
+use std::future::Future;
+use std::ops::Generator;
+use std::pin::Pin;
+
+fn plain() -> impl Generator<(), Yield = u32, Return = ()> {
+    || {
+        yield 1;
+    }
+}
+
+fn with_arg() -> impl Generator<u32, Yield = u32, Return = ()> {
+    |x: u32| {
+        yield x;
+    }
+}
+
+fn async_block() -> impl Future<Output = u32> {
+    async { 1 }
+}
+
+fn main() {
+    let mut g = plain();
+    Pin::new(&mut g).resume(());
+    let mut h = with_arg();
+    Pin::new(&mut h).resume(2);
+    let _f = async_block();
+}
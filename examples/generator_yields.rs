@@ -0,0 +1,25 @@
+#![feature(generators, generator_trait)]
+
+use std::ops::{Generator, GeneratorState};
+use std::pin::Pin;
+
+// Two suspend points, so the body has two `Yield` terminators, each with its own resume block.
+fn two_yields() -> impl Generator<u32, Yield = u32, Return = u32> {
+    |first: u32| {
+        let second = yield first + 1;
+        let third = yield second * 2;
+        third
+    }
+}
+
+fn drive() -> u32 {
+    let mut g = two_yields();
+    let mut total = 0;
+    for arg in [1, 2, 3] {
+        match Pin::new(&mut g).resume(arg) {
+            GeneratorState::Yielded(x) => total += x,
+            GeneratorState::Complete(x) => total += x,
+        }
+    }
+    total
+}
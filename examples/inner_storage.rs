@@ -0,0 +1,10 @@
+// `y` is only live inside the inner block, so its `StorageLive` and `StorageDead` statements both
+// come before the end of the function.  The argument `x` has no storage statements at all.
+fn inner_block(x: u32) -> u32 {
+    let mut total = x;
+    {
+        let y = x * 2;
+        total += y;
+    }
+    total + 1
+}
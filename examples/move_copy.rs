@@ -0,0 +1,14 @@
+fn take(s: String) -> usize {
+    s.len()
+}
+
+fn f() -> usize {
+    let s = String::from("hello");
+    let n = 1_usize;
+    let m = n;
+    take(s) + n + m
+}
+
+fn main() {
+    f();
+}
@@ -0,0 +1,10 @@
+use std::num::NonZeroU8;
+
+// `NonZeroU8` has a valid range of `1..=255`, which also gives `Option<NonZeroU8>` a niche at 0.
+fn nonzero(x: NonZeroU8) -> u8 {
+    x.get()
+}
+
+fn maybe_nonzero(x: u8) -> Option<NonZeroU8> {
+    NonZeroU8::new(x)
+}
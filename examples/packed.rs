@@ -0,0 +1,26 @@
+#[repr(packed)]
+struct P {
+    a: u8,
+    b: u32,
+}
+
+#[repr(packed(2))]
+struct P2 {
+    a: u8,
+    b: u32,
+}
+
+fn read_b(p: &P) -> u32 {
+    p.a as u32 + p.b
+}
+
+fn read_b2(p: &P2) -> u32 {
+    p.a as u32 + p.b
+}
+
+fn main() {
+    let p = P { a: 1, b: 2 };
+    let p2 = P2 { a: 1, b: 2 };
+    read_b(&p);
+    read_b2(&p2);
+}
@@ -0,0 +1,28 @@
+// `E` is laid out as a tag followed by a union of the payloads, so it always uses a `Direct` tag
+// encoding.  `Plain` has the same variants with the default repr, which leaves rustc free to
+// choose the layout.
+
+#[repr(C)]
+enum E {
+    A(u32),
+    B(u64),
+}
+
+enum Plain {
+    A(u32),
+    B(u64),
+}
+
+fn get_e(e: &E) -> u64 {
+    match *e {
+        E::A(x) => x as u64,
+        E::B(x) => x,
+    }
+}
+
+fn get_plain(p: &Plain) -> u64 {
+    match *p {
+        Plain::A(x) => x as u64,
+        Plain::B(x) => x,
+    }
+}
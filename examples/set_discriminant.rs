@@ -0,0 +1,13 @@
+// Writing a whole `Option` into a place initializes the payload field and then sets the variant
+// with `SetDiscriminant`.
+fn fill(out: &mut Option<u32>, x: u32) {
+    *out = Some(x);
+}
+
+fn clear(out: &mut Option<u32>) {
+    *out = None;
+}
+
+fn make_pair(x: u32) -> (Option<u32>, Option<u32>) {
+    (Some(x), None)
+}
@@ -0,0 +1,28 @@
+#![feature(repr_simd)]
+
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::{__m128i, __m256d, _mm_set1_epi32};
+
+// `__m128i` is declared with two `i64` lanes, and `__m256d` with four `f64` lanes.
+#[cfg(target_arch = "x86_64")]
+fn arch_vectors(a: __m128i, b: __m256d) -> (__m128i, __m256d) {
+    (a, b)
+}
+
+#[cfg(target_arch = "x86_64")]
+fn splat(x: i32) -> __m128i {
+    unsafe { _mm_set1_epi32(x) }
+}
+
+// A vector declared with an array field, and one declared with one field per lane.
+#[repr(simd)]
+#[derive(Clone, Copy)]
+struct U32x4([u32; 4]);
+
+#[repr(simd)]
+#[derive(Clone, Copy)]
+struct F32x2(f32, f32);
+
+fn local_vectors(a: U32x4, b: F32x2) -> (U32x4, F32x2) {
+    (a, b)
+}
@@ -0,0 +1,11 @@
+static TUPLE: (i32, i32) = (1, 2);
+static P: &i32 = &TUPLE.1;
+static Q: &&i32 = &P;
+
+fn f() -> i32 {
+    *P + **Q
+}
+
+fn main() {
+    f();
+}
@@ -0,0 +1,32 @@
+trait A {
+    fn a(&self) -> u32;
+}
+
+trait B: A {
+    fn sized_only(&self) -> u32 where Self: Sized { 0 }
+    fn b(&self) -> u32;
+}
+
+struct S;
+
+impl A for S {
+    fn a(&self) -> u32 { 1 }
+}
+
+impl B for S {
+    fn b(&self) -> u32 { 2 }
+}
+
+fn call_a(x: &dyn B) -> u32 {
+    x.a()
+}
+
+fn call_b(x: &dyn B) -> u32 {
+    x.b()
+}
+
+fn main() {
+    let s = S;
+    call_a(&s);
+    call_b(&s);
+}
@@ -0,0 +1,25 @@
+#![feature(thread_local)]
+
+use std::cell::Cell;
+
+thread_local! {
+    static COUNTER: Cell<u32> = Cell::new(0);
+}
+
+#[thread_local]
+static mut RAW: u32 = 0;
+
+fn bump() -> u32 {
+    COUNTER.with(|c| {
+        c.set(c.get() + 1);
+        c.get()
+    })
+}
+
+fn read_raw() -> u32 {
+    unsafe { RAW }
+}
+
+fn write_raw(x: u32) {
+    unsafe { RAW = x; }
+}
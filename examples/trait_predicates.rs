@@ -0,0 +1,16 @@
+// The trait's own predicates should include both `T: Clone` and `Self: Sized`.
+trait Foo<T: Clone> where Self: Sized {
+    fn foo(self, x: &T) -> T;
+}
+
+struct S;
+
+impl Foo<u32> for S {
+    fn foo(self, x: &u32) -> u32 {
+        x.clone()
+    }
+}
+
+fn call_foo(s: S, x: u32) -> u32 {
+    s.foo(&x)
+}
@@ -0,0 +1,19 @@
+use std::marker::PhantomData;
+use std::num::Wrapping;
+
+// The zero-sized marker comes first, so `transparent_field` should point at field 1.
+#[repr(transparent)]
+struct Meters<T> {
+    _unit: PhantomData<T>,
+    value: f64,
+}
+
+struct Metric;
+
+fn wrapping_add(a: Wrapping<u32>, b: Wrapping<u32>) -> Wrapping<u32> {
+    a + b
+}
+
+fn meters(m: Meters<Metric>) -> f64 {
+    m.value
+}
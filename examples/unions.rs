@@ -0,0 +1,13 @@
+union U {
+    a: u32,
+    b: f32,
+}
+
+fn f(x: u32) -> f32 {
+    let u = U { a: x };
+    unsafe { u.b }
+}
+
+fn main() {
+    f(1);
+}
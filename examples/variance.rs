@@ -0,0 +1,19 @@
+use std::marker::PhantomData;
+
+// Run with `--variances`.  `Contra` is contravariant in `T`, since `T` appears only as a function
+// argument, while `Co` is covariant in `T`, like the `Vec<T>` it holds.
+struct Contra<T> {
+    _marker: PhantomData<fn(T)>,
+}
+
+struct Co<T> {
+    items: Vec<T>,
+}
+
+fn contra(c: Contra<&'static str>) -> Contra<&'static str> {
+    c
+}
+
+fn co(c: Co<u8>) -> usize {
+    c.items.len()
+}
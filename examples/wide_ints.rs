@@ -0,0 +1,10 @@
+const U_MAX: u128 = u128::MAX;
+const I_MIN: i128 = i128::MIN;
+
+fn f() -> (u128, i128) {
+    (U_MAX, I_MIN)
+}
+
+fn main() {
+    f();
+}
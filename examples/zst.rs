@@ -0,0 +1,12 @@
+use std::marker::PhantomData;
+
+// Both of these are zero-sized, and `PhantomData` also records its type parameter.
+struct Unit;
+
+fn phantom(p: PhantomData<u8>) -> PhantomData<u8> {
+    p
+}
+
+fn unit(u: Unit) -> Unit {
+    u
+}
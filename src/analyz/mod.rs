@@ -1,25 +1,28 @@
 #![macro_use]
 
 use rustc_ast::{ast, token, tokenstream, visit, ptr, Crate};
+use rustc_ast::expand::allocator::AllocatorKind;
 use rustc_hir::def::DefKind;
 use rustc_hir::def_id::{DefId, LOCAL_CRATE};
 use rustc_index::vec::Idx;
 use rustc_interface::Queries;
+use rustc_middle::middle::codegen_fn_attrs::CodegenFnAttrFlags;
+use rustc_middle::middle::exported_symbols::ExportedSymbol;
 use rustc_middle::ty::{self, TyCtxt, List};
-use rustc_middle::mir::{self, Body};
-use rustc_middle::mir::mono::MonoItem;
+use rustc_middle::mir::{self, Body, interpret};
+use rustc_middle::mir::mono::{Linkage, MonoItem};
 use rustc_session::{self, Session};
-use rustc_session::config::OutputType;
+use rustc_session::config::{CrateType, OutputType};
 use rustc_span::Span;
-use rustc_span::symbol::{Symbol, Ident};
+use rustc_span::symbol::{sym, Symbol, Ident};
 use rustc_target::abi;
 use rustc_target::spec;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Write as FmtWrite;
 use std::io;
 use std::iter;
-use std::fs::File;
+use std::fs::{self, File};
 use std::mem;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
@@ -69,9 +72,16 @@ impl<'tcx> ToJson<'tcx> for mir::AggregateKind<'tcx> {
                     // tuples, so no additional information is needed.
                 })
             }
-            &mir::AggregateKind::Generator(_, _, _,) => {
-                // TODO
-                json!({"kind": "Generator"})
+            &mir::AggregateKind::Generator(def_id, substs, movability) => {
+                // As with closures, the operands are the generator's upvars.  The generator type
+                // carries the layout of its saved locals and the rest of its state.
+                let ty = mir.state.tcx.mk_generator(def_id, substs, movability);
+                json!({
+                    "kind": "Generator",
+                    "def_id": def_id.to_json(mir),
+                    "ty": ty.to_json(mir),
+                    "movability": movability.to_json(mir),
+                })
             }
         }
     }
@@ -79,13 +89,13 @@ impl<'tcx> ToJson<'tcx> for mir::AggregateKind<'tcx> {
 
 /// Compute the "vtable descriptor" for a given cast, if applicable.  We identify vtables by their
 /// `PolyTraitRef`s, which uniquely determine the trait and self type, and which can be used by
-/// downstream code such as `build_vtable`.
+/// downstream code such as `build_vtable`, along with the auto traits of the target `dyn` type.
 fn vtable_descriptor_for_cast<'tcx>(
     mir: &mut MirState<'_, 'tcx>,
     kind: mir::CastKind,
     old_ty: ty::Ty<'tcx>,
     new_ty: ty::Ty<'tcx>,
-) -> Option<ty::PolyTraitRef<'tcx>> {
+) -> Option<(ty::PolyTraitRef<'tcx>, Vec<DefId>)> {
     let tcx = mir.state.tcx;
 
     if kind != mir::CastKind::Pointer(ty::adjustment::PointerCast::Unsize) {
@@ -110,9 +120,11 @@ fn vtable_descriptor_for_cast<'tcx>(
     }
 
     // Relevant code: rustc_codegen_ssa::meth::get_vtable
-    let trait_ref = match *new_pointee.kind() {
-        ty::TyKind::Dynamic(ref preds, _, _) =>
+    let (trait_ref, auto_traits) = match *new_pointee.kind() {
+        ty::TyKind::Dynamic(preds, _, _) => (
             preds.principal().map(|pred| pred.with_self_ty(tcx, old_pointee)),
+            TraitInst::from_dynamic_predicates(tcx, preds).auto_traits,
+        ),
         _ => return None,
     };
     let trait_ref: ty::PolyTraitRef = match trait_ref {
@@ -122,7 +134,62 @@ fn vtable_descriptor_for_cast<'tcx>(
         // TODO: handle this better (currently the output omits the "vtable" field)
         None => return None,
     };
-    Some(trait_ref)
+    Some((trait_ref, auto_traits))
+}
+
+/// Describe an unsizing coercion from `old_ty` to `new_ty`, which may be references, raw pointers,
+/// or smart pointers such as `Box` and `Rc`.  `source` and `target` are the pointee types, such as
+/// `[u8; 4]` and `[u8]`, or `Foo<[u8; 4]>` and `Foo<[u8]>`.  `source_tail` and `target_tail` are
+/// the parts that actually change, whose relationship determines the metadata of the new fat
+/// pointer: `len` is the length of the array, when coercing an array to a slice.  For coercions
+/// to `dyn Trait`, the vtable is given in the cast kind.
+fn unsize_json<'tcx>(
+    mir: &mut MirState<'_, 'tcx>,
+    old_ty: ty::Ty<'tcx>,
+    new_ty: ty::Ty<'tcx>,
+) -> Option<serde_json::Value> {
+    let tcx = mir.state.tcx;
+    let (source, target) = unsize_pointees(tcx, old_ty, new_ty)?;
+    let (source_tail, target_tail) = tcx.struct_lockstep_tails_erasing_lifetimes(
+        source, target, ty::ParamEnv::reveal_all());
+    let len = match (source_tail.kind(), target_tail.kind()) {
+        (&ty::TyKind::Array(_, len), &ty::TyKind::Slice(_)) =>
+            len.try_eval_usize(tcx, ty::ParamEnv::reveal_all()),
+        _ => None,
+    };
+    Some(json!({
+        "source": source.to_json(mir),
+        "target": target.to_json(mir),
+        "source_tail": source_tail.to_json(mir),
+        "target_tail": target_tail.to_json(mir),
+        "len": len,
+    }))
+}
+
+/// Find the pointee types of the pointers `old_ty` and `new_ty` in an unsizing coercion.  For a
+/// smart pointer, this looks through the one field that changes type (as `CoerceUnsized`
+/// requires) until it reaches a builtin pointer.
+fn unsize_pointees<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    old_ty: ty::Ty<'tcx>,
+    new_ty: ty::Ty<'tcx>,
+) -> Option<(ty::Ty<'tcx>, ty::Ty<'tcx>)> {
+    match (old_ty.kind(), new_ty.kind()) {
+        (&ty::TyKind::Adt(old_adt, old_substs), &ty::TyKind::Adt(new_adt, new_substs))
+                if old_adt == new_adt && !old_adt.is_box() => {
+            old_adt.non_enum_variant().fields.iter().find_map(|f| {
+                let old_fty = tcx.normalize_erasing_regions(
+                    ty::ParamEnv::reveal_all(), f.ty(tcx, old_substs));
+                let new_fty = tcx.normalize_erasing_regions(
+                    ty::ParamEnv::reveal_all(), f.ty(tcx, new_substs));
+                if old_fty == new_fty {
+                    return None;
+                }
+                unsize_pointees(tcx, old_fty, new_fty)
+            })
+        },
+        _ => Some((old_ty.builtin_deref(true)?.ty, new_ty.builtin_deref(true)?.ty)),
+    }
 }
 
 impl<'tcx> ToJson<'tcx> for mir::Rvalue<'tcx> {
@@ -135,10 +202,17 @@ impl<'tcx> ToJson<'tcx> for mir::Rvalue<'tcx> {
                 })
             }
             &mir::Rvalue::Repeat(ref op, s) => {
+                // `len` is a plain integer when the count is known.  Otherwise (as for `[0; N]`
+                // with a const generic `N`), it's a reference to the const parameter, in the same
+                // form as the `size` of an `Array` type.
+                let len = match s.try_eval_usize(mir.state.tcx, ty::ParamEnv::reveal_all()) {
+                    Some(n) => json!(n),
+                    None => array_len_json(mir, s),
+                };
                 json!({
                     "kind": "Repeat",
                     "op": op.to_json(mir),
-                    "len": get_const_usize(mir.state.tcx, s)
+                    "len": len,
                 })
             }
             &mir::Rvalue::Ref(_, ref bk, ref l) => {
@@ -150,12 +224,17 @@ impl<'tcx> ToJson<'tcx> for mir::Rvalue<'tcx> {
                 })
             }
             &mir::Rvalue::ThreadLocalRef(did) => {
+                // Thread-locals aren't roots on their own when using `--filter`, so mark this one
+                // as used to make sure its declaration is emitted.
+                mir.used.statics.insert(did);
                 json!({
                     "kind": "ThreadLocalRef",
                     "def_id": did.to_json(mir),
                     "ty": mir.state.tcx.static_ptr_ty(did).to_json(mir),
                 })
             }
+            // Unlike `Ref`, `&raw const` and `&raw mut` don't require the place to be aligned
+            // or initialized, so taking the address of a packed field is fine.
             &mir::Rvalue::AddressOf(mutbl, ref l) => {
                 json!({
                     "kind": "AddressOf",
@@ -178,10 +257,13 @@ impl<'tcx> ToJson<'tcx> for mir::Rvalue<'tcx> {
                     // On the Haskell side, the vtable is attached to the cast kind.
                     j["type"] = json!({
                         "kind": "UnsizeVtable",
-                        "vtable": vtable_name(mir, vtable_desc),
+                        "vtable": vtable_name(mir, &vtable_desc),
                     });
                     mir.used.vtables.insert(vtable_desc);
                 }
+                if *ck == mir::CastKind::Pointer(ty::adjustment::PointerCast::Unsize) {
+                    j["unsize"] = json!(unsize_json(mir, op_ty, ty));
+                }
                 j
             }
             &mir::Rvalue::BinaryOp(ref binop, ref ops) => {
@@ -201,10 +283,19 @@ impl<'tcx> ToJson<'tcx> for mir::Rvalue<'tcx> {
                 })
             }
             &mir::Rvalue::NullaryOp(ref no, ref t) => {
+                // Bodies are monomorphized, so the layout of `t` is almost always known, and we
+                // emit the result directly.  `value` is `null` if the layout can't be computed,
+                // leaving the consumer to evaluate `op` itself.
+                let value = mir.state.tcx.layout_of(ty::ParamEnv::reveal_all().and(*t)).ok()
+                    .map(|layout| match no {
+                        mir::NullOp::SizeOf => layout.size.bytes(),
+                        mir::NullOp::AlignOf => layout.align.abi.bytes(),
+                    });
                 json!({
                     "kind": "NullaryOp",
                     "op": no.to_json(mir),
-                    "ty": t.to_json(mir)
+                    "ty": t.to_json(mir),
+                    "value": value,
                 })
             }
             &mir::Rvalue::UnaryOp(ref uo, ref o) => {
@@ -215,13 +306,14 @@ impl<'tcx> ToJson<'tcx> for mir::Rvalue<'tcx> {
                 })
             }
             &mir::Rvalue::Discriminant(ref lv) => {
+                // `ty` is the type of the discriminant value, and `enum_ty` is the type being
+                // discriminated (usually an enum, though generators also have discriminants).
+                let enum_ty = lv.ty(mir.mir.unwrap(), mir.state.tcx).ty;
                 json!({
                     "kind": "Discriminant",
                     "val": lv.to_json(mir),
-                    "ty": lv.ty(mir.mir.unwrap(), mir.state.tcx)
-                            .ty
-                            .discriminant_ty(mir.state.tcx)
-                            .to_json(mir),
+                    "ty": enum_ty.discriminant_ty(mir.state.tcx).to_json(mir),
+                    "enum_ty": enum_ty.to_json(mir),
                 })
             }
             &mir::Rvalue::Aggregate(ref ak, ref opv) => {
@@ -231,9 +323,16 @@ impl<'tcx> ToJson<'tcx> for mir::Rvalue<'tcx> {
                         "ag": ty_json::handle_adt_ag (mir, ak, opv)
                     })
                 } else {
+                    let mut akind = ak.to_json(mir);
+                    if let mir::AggregateKind::Array(_) = **ak {
+                        // The array's length is the number of operands.  We emit it explicitly
+                        // so that consumers can allocate the array, including empty arrays,
+                        // without inspecting them.
+                        akind["len"] = opv.len().into();
+                    }
                     json!({
                         "kind": "Aggregate",
-                        "akind": ak.to_json(mir),
+                        "akind": akind,
                         "ops": opv.to_json(mir)
                     })
                 }
@@ -257,13 +356,85 @@ impl<'tcx> ToJson<'tcx> for mir::Rvalue<'tcx> {
 
 impl<'tcx> ToJson<'tcx> for mir::Place<'tcx> {
     fn to_json(&self, mir: &mut MirState<'_, 'tcx>) -> serde_json::Value {
+        let mut data = self.projection.to_json(mir);
+        if mir.opts.place_align {
+            let tcx = mir.state.tcx;
+            let mut place_ty = mir::PlaceTy::from_ty(mir.mir.unwrap().local_decls[self.local].ty);
+            for (i, &elem) in self.projection.iter().enumerate() {
+                if let Some(align) = place_elem_align(tcx, place_ty.ty, elem) {
+                    data[i]["align"] = align.into();
+                }
+                place_ty = place_ty.projection_ty(tcx, elem);
+            }
+        }
         json!({
             "var": local_json(mir, self.local),
-            "data" : self.projection.to_json(mir)
+            "data" : data
         })
     }
 }
 
+/// Describe the `Box` allocated by a call to `exchange_malloc` that writes its result to `dest`.
+/// `box` expressions (including `Box::new`) lower to such a call, followed by a `ShallowInitBox`
+/// that turns the raw pointer into a `Box<T>` before `T` is written into it.  We find that
+/// `ShallowInitBox` to get `T`, which is `null` if it can't be found.
+fn box_alloc_json<'tcx>(
+    mir: &mut MirState<'_, 'tcx>,
+    dest: mir::Place<'tcx>,
+) -> serde_json::Value {
+    let body = mir.mir.unwrap();
+    let boxed_ty = body.basic_blocks.iter()
+        .flat_map(|bb| bb.statements.iter())
+        .find_map(|stmt| match stmt.kind {
+            mir::StatementKind::Assign(ref assign) => match assign.1 {
+                mir::Rvalue::ShallowInitBox(ref op, ty) if op.place() == Some(dest) => Some(ty),
+                _ => None,
+            },
+            _ => None,
+        });
+    json!({
+        "kind": "Box",
+        "ty": boxed_ty.map(|ty| ty.to_json(mir)),
+    })
+}
+
+/// Get the alignment in bytes that projecting `elem` out of a place of type `base_ty` requires,
+/// for the projections where it might not hold: a `Deref` of a raw pointer, and a `Field` of a
+/// packed struct, whose fields are aligned to at most the packing.  Other projections return
+/// `None`, as does a `Deref` whose pointee has no statically known alignment (such as a trait
+/// object).
+fn place_elem_align<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    base_ty: ty::Ty<'tcx>,
+    elem: mir::PlaceElem<'tcx>,
+) -> Option<u64> {
+    let align_of = |ty: ty::Ty<'tcx>| {
+        tcx.layout_of(ty::ParamEnv::reveal_all().and(ty)).ok().map(|l| l.align.abi)
+    };
+    match elem {
+        mir::ProjectionElem::Deref => {
+            let pointee = match *base_ty.kind() {
+                ty::TyKind::RawPtr(tm) => tm.ty,
+                _ => return None,
+            };
+            let known = tcx.is_sized_raw(ty::ParamEnv::reveal_all().and(pointee)) ||
+                matches!(pointee.kind(), ty::TyKind::Slice(_) | ty::TyKind::Str);
+            if !known {
+                return None;
+            }
+            align_of(pointee).map(|a| a.bytes())
+        },
+        mir::ProjectionElem::Field(_, fty) => {
+            let pack = match *base_ty.kind() {
+                ty::TyKind::Adt(adt, _) => adt.repr().pack?,
+                _ => return None,
+            };
+            align_of(fty).map(|a| a.min(pack).bytes())
+        },
+        _ => None,
+    }
+}
+
 impl<'tcx> ToJson<'tcx> for mir::PlaceElem<'tcx> {
     fn to_json(&self, mir: &mut MirState<'_, 'tcx>) -> serde_json::Value {
         match self {
@@ -322,6 +493,8 @@ basic_json_impl!(mir::AssertMessage<'a>, 'a);
 
 impl<'tcx> ToJson<'tcx> for mir::Operand<'tcx> {
     fn to_json(&self, mir: &mut MirState<'_, 'tcx>) -> serde_json::Value {
+        // `Copy` and `Move` must stay distinct: after a `Move`, the source place is uninitialized
+        // (unless its type is `Copy`), which matters to consumers that track initialization.
         match self {
             &mir::Operand::Copy(ref l) => {
                 json!({"kind": "Copy", "data": l.to_json(mir)})
@@ -338,6 +511,38 @@ impl<'tcx> ToJson<'tcx> for mir::Operand<'tcx> {
 
 impl<'tcx> ToJson<'tcx> for mir::Constant<'tcx> {
     fn to_json(&self, mir: &mut MirState<'_, 'tcx>) -> serde_json::Value {
+        if mir.opts.lazy_consts {
+            let uv = match self.literal {
+                mir::ConstantKind::Ty(ct) => match ct.kind() {
+                    ty::ConstKind::Unevaluated(uv) => Some(uv.expand()),
+                    _ => None,
+                },
+                mir::ConstantKind::Unevaluated(uv, _) => Some(uv),
+                mir::ConstantKind::Val(..) => None,
+            };
+            if let Some(uv) = uv {
+                let mut rendered = json!({
+                    "kind": "unevaluated",
+                    "def_id": get_promoted_name(mir, uv.def.did, uv.substs, uv.promoted),
+                    "substs": uv.substs.to_json(mir),
+                });
+                // Associated consts are usually simple values that consumers want without
+                // evaluating the initializer themselves, so we include the value when it's
+                // available.  Evaluation fails when the const can't be resolved to a value, such
+                // as a trait const with no default whose impl isn't known yet, in which case the
+                // value is omitted.
+                let tcx = mir.state.tcx;
+                if tcx.def_kind(uv.def.did) == DefKind::AssocConst {
+                    if let Ok(val) = tcx.const_eval_resolve(ty::ParamEnv::reveal_all(), uv, None) {
+                        rendered["value"] = render_const_value(mir, val, self.ty());
+                    }
+                }
+                return json!({
+                    "ty": self.ty().to_json(mir),
+                    "rendered": rendered,
+                });
+            }
+        }
         (eval_mir_constant(mir.state.tcx, self), self.ty()).to_json(mir)
     }
 }
@@ -365,9 +570,19 @@ impl<'tcx> ToJson<'tcx> for mir::Statement<'tcx> {
                     "rhs": assign.1.to_json(mir)
                 })
             }
-            &mir::StatementKind::FakeRead { .. } => {
-                // TODO
-                json!({"kind": "FakeRead"})
+            &mir::StatementKind::FakeRead(ref cause_place) => {
+                // Borrowck removes these before optimized MIR is built, so they appear only in
+                // the bodies of constants obtained before that point.  They have no effect at
+                // runtime.
+                let (ref cause, ref place) = **cause_place;
+                let cause = match *cause {
+                    mir::FakeReadCause::ForMatchGuard => "ForMatchGuard",
+                    mir::FakeReadCause::ForMatchedPlace(_) => "ForMatchedPlace",
+                    mir::FakeReadCause::ForGuardBinding => "ForGuardBinding",
+                    mir::FakeReadCause::ForLet(_) => "ForLet",
+                    mir::FakeReadCause::ForIndex => "ForIndex",
+                };
+                json!({"kind": "FakeRead", "cause": cause, "place": place.to_json(mir)})
             }
             &mir::StatementKind::Deinit { .. } => {
                 // TODO
@@ -377,10 +592,17 @@ impl<'tcx> ToJson<'tcx> for mir::Statement<'tcx> {
                 ref place,
                 ref variant_index,
             } => {
+                // Also give the discriminant value of the variant, which is what a later
+                // `Rvalue::Discriminant` on the same place will read.
+                let tcx = mir.state.tcx;
+                let ty = place.ty(mir.mir.unwrap(), tcx).ty;
+                let discr = ty.discriminant_for_variant(tcx, *variant_index)
+                    .map(|d| d.val.to_string());
                 json!({
                     "kind": "SetDiscriminant",
                     "lvalue": place.to_json(mir),
-                    "variant_index": variant_index.to_json(mir)
+                    "variant_index": variant_index.to_json(mir),
+                    "discr_value": discr,
                 })
             }
             &mir::StatementKind::StorageLive(l) => {
@@ -389,9 +611,14 @@ impl<'tcx> ToJson<'tcx> for mir::Statement<'tcx> {
             &mir::StatementKind::StorageDead(l) => {
                 json!({"kind": "StorageDead", "sdvar": local_json(mir, l)})
             }
-            &mir::StatementKind::Retag { .. } => {
-                // TODO
-                json!({"kind": "Retag"})
+            &mir::StatementKind::Retag(kind, ref place) => {
+                // rustc only emits these with `-Z mir-emit-retag`, for use by Stacked Borrows
+                // checkers.  `retag_kind` is one of `FnEntry`, `TwoPhase`, `Raw`, or `Default`.
+                json!({
+                    "kind": "Retag",
+                    "retag_kind": format!("{:?}", kind),
+                    "place": place.to_json(mir),
+                })
             }
             &mir::StatementKind::AscribeUserType { .. } => {
                 // TODO
@@ -426,6 +653,9 @@ impl<'tcx> ToJson<'tcx> for mir::Statement<'tcx> {
             }
         };
         j["pos"] = self.source_info.span.to_json(mir);
+        if mir.opts.source_scopes {
+            j["scope"] = self.source_info.scope.as_usize().into();
+        }
         j
     }
 }
@@ -497,7 +727,7 @@ impl<'tcx> ToJson<'tcx> for mir::Terminator<'tcx> {
                     "kind": "Drop",
                     "location": location.to_json(mir),
                     "target" : target.to_json(mir),
-                    "unwind": unwind.to_json(mir),
+                    "unwind": unwind_json(mir, *unwind),
                     "drop_fn": get_drop_fn_name(mir, ty),
                 })
             }
@@ -513,7 +743,7 @@ impl<'tcx> ToJson<'tcx> for mir::Terminator<'tcx> {
                     "location": location.to_json(mir),
                     "value": value.to_json(mir),
                     "target": target.to_json(mir),
-                    "unwind": unwind.to_json(mir),
+                    "unwind": unwind_json(mir, *unwind),
                     "drop_fn": get_drop_fn_name(mir, ty),
                 })
             }
@@ -532,13 +762,32 @@ impl<'tcx> ToJson<'tcx> for mir::Terminator<'tcx> {
                         dest_block.to_json(mir),
                     ])
                 });
+                // For calls to C-variadic functions, such as `printf`, record how many of the
+                // arguments are fixed parameters.  The rest are passed as variadic arguments,
+                // which the ABI treats differently.
+                let tcx = mir.state.tcx;
+                let func_ty = func.ty(mir.mir.unwrap(), tcx);
+                let fixed_args = if func_ty.is_fn() {
+                    let sig = func_ty.fn_sig(tcx).skip_binder();
+                    if sig.c_variadic { Some(sig.inputs().len()) } else { None }
+                } else {
+                    None
+                };
+                let box_alloc = match *func_ty.kind() {
+                    ty::TyKind::FnDef(did, _)
+                        if Some(did) == tcx.lang_items().exchange_malloc_fn() =>
+                            Some(box_alloc_json(mir, *dest_place)),
+                    _ => None,
+                };
                 json!({
                     "kind": "Call",
                     "func": func.to_json(mir),
                     "args": args.to_json(mir),
                     "destination": destination,
-                    "cleanup": cleanup.to_json(mir),
-                    "from_hir_call": from_hir_call
+                    "cleanup": unwind_json(mir, *cleanup),
+                    "from_hir_call": from_hir_call,
+                    "fixed_args": fixed_args,
+                    "box_alloc": box_alloc,
                 })
             }
             &mir::TerminatorKind::Assert {
@@ -553,17 +802,27 @@ impl<'tcx> ToJson<'tcx> for mir::Terminator<'tcx> {
                     "cond": cond.to_json(mir),
                     "expected": expected,
                     "msg": msg.to_json(mir),
+                    "assert_kind": assert_kind_json(mir, msg),
                     "target": target.to_json(mir),
-                    "cleanup": cleanup.to_json(mir)
+                    "cleanup": unwind_json(mir, *cleanup),
                 })
             }
             &mir::TerminatorKind::Abort => {
                 json!({ "kind": "Abort" })
             }
-            &mir::TerminatorKind::Yield { .. } => {
-                // TODO
+            &mir::TerminatorKind::Yield { ref value, resume, ref resume_arg, drop } => {
+                // Suspends the generator, yielding `value`.  When the generator is resumed,
+                // the resume argument is written to `resume_arg` and execution continues at
+                // `resume`.  If the generator is dropped while suspended here, execution
+                // continues at `drop` instead, which eventually reaches `GeneratorDrop`.  These
+                // appear only in generator bodies that have not yet been lowered to a state
+                // machine.
                 json!({
-                    "kind": "Yield"
+                    "kind": "Yield",
+                    "value": value.to_json(mir),
+                    "resume": resume.to_json(mir),
+                    "resume_arg": resume_arg.to_json(mir),
+                    "drop": drop.map(|bb| bb.to_json(mir)),
                 })
             }
             &mir::TerminatorKind::FalseEdge { .. } => {
@@ -572,25 +831,89 @@ impl<'tcx> ToJson<'tcx> for mir::Terminator<'tcx> {
                     "kind": "FalseEdge"
                 })
             }
-            &mir::TerminatorKind::FalseUnwind { .. } => {
-                // TODO
+            &mir::TerminatorKind::FalseUnwind { ref real_target, ref unwind } => {
                 json!({
-                    "kind": "FalseUnwind"
+                    "kind": "FalseUnwind",
+                    "real_target": real_target.to_json(mir),
+                    "unwind": unwind_json(mir, *unwind),
                 })
             }
             &mir::TerminatorKind::GeneratorDrop => {
+                // Returns from the generator's drop glue, after dropping the state saved at a
+                // `Yield`.
                 json!({ "kind": "GeneratorDrop" })
             }
-            &mir::TerminatorKind::InlineAsm { .. } => {
-                // TODO
-                json!({"kind": "InlineAsm"})
+            &mir::TerminatorKind::InlineAsm { ref destination, ref cleanup, .. } => {
+                // TODO: emit the template and operands
+                mir.note_missing("InlineAsm terminator");
+                json!({
+                    "kind": "InlineAsm",
+                    "destination": destination.to_json(mir),
+                    "cleanup": unwind_json(mir, *cleanup),
+                })
             }
         };
         j["pos"] = self.source_info.span.to_json(mir);
+        if mir.opts.source_scopes {
+            j["scope"] = self.source_info.scope.as_usize().into();
+        }
         j
     }
 }
 
+/// Describe the panic raised when an `Assert` fails.  `msg` is also emitted in its `Debug` form,
+/// but this gives the operands separately, so consumers can reproduce rustc's panic messages
+/// (such as "index out of bounds: the len is 3 but the index is 5").
+fn assert_kind_json<'tcx>(
+    mir: &mut MirState<'_, 'tcx>,
+    msg: &mir::AssertMessage<'tcx>,
+) -> serde_json::Value {
+    match msg {
+        mir::AssertKind::BoundsCheck { len, index } => json!({
+            "kind": "BoundsCheck",
+            "len": len.to_json(mir),
+            "index": index.to_json(mir),
+        }),
+        mir::AssertKind::Overflow(op, l, r) => json!({
+            "kind": "Overflow",
+            "op": op.to_json(mir),
+            "left": l.to_json(mir),
+            "right": r.to_json(mir),
+        }),
+        mir::AssertKind::OverflowNeg(x) => json!({
+            "kind": "OverflowNeg",
+            "arg": x.to_json(mir),
+        }),
+        mir::AssertKind::DivisionByZero(x) => json!({
+            "kind": "DivisionByZero",
+            "arg": x.to_json(mir),
+        }),
+        mir::AssertKind::RemainderByZero(x) => json!({
+            "kind": "RemainderByZero",
+            "arg": x.to_json(mir),
+        }),
+        mir::AssertKind::ResumedAfterReturn(gk) => json!({
+            "kind": "ResumedAfterReturn",
+            "generator_kind": format!("{:?}", gk),
+        }),
+        mir::AssertKind::ResumedAfterPanic(gk) => json!({
+            "kind": "ResumedAfterPanic",
+            "generator_kind": format!("{:?}", gk),
+        }),
+    }
+}
+
+/// Serialize the unwind edge of a terminator, which leads to the cleanup code that runs if the
+/// terminator panics.  Under `-C panic=abort`, a panic aborts instead, so we omit unwind edges
+/// entirely, whether or not rustc kept the cleanup blocks.  Schema version 1 always keeps them.
+fn unwind_json(mir: &mut MirState, bb: Option<mir::BasicBlock>) -> serde_json::Value {
+    if mir.opts.schema() >= 2 &&
+            mir.state.tcx.sess.panic_strategy() == spec::PanicStrategy::Abort {
+        return serde_json::Value::Null;
+    }
+    bb.to_json(mir)
+}
+
 impl<'tcx> ToJson<'tcx> for mir::BasicBlockData<'tcx> {
     fn to_json(&self, mir: &mut MirState<'_, 'tcx>) -> serde_json::Value {
         let mut sts = Vec::new();
@@ -605,10 +928,23 @@ impl<'tcx> ToJson<'tcx> for mir::BasicBlockData<'tcx> {
 }
 
 fn local_json(ms: &mut MirState, local: mir::Local) -> serde_json::Value {
-    let mut j = ms.mir.unwrap().local_decls[local].to_json(ms); // TODO
+    let mir = ms.mir.unwrap();
+    let mut j = mir.local_decls[local].to_json(ms); // TODO
     let mut s = String::new();
     write!(&mut s, "{:?}", local).unwrap();
     j["name"] = json!(s);
+    // One of `ReturnPointer`, `Arg`, `Var` (a user variable), or `Temp`.
+    j["local_kind"] = json!(format!("{:?}", mir.local_kind(local)));
+    // The name of the source variable stored directly in this local, if any.  This is omitted
+    // when there is no such variable, to keep the output small.
+    let debug_name = mir.var_debug_info.iter().find_map(|vdi| match vdi.value {
+        mir::VarDebugInfoContents::Place(p) if p.local == local && p.projection.is_empty() =>
+            Some(vdi.name),
+        _ => None,
+    });
+    if let Some(debug_name) = debug_name {
+        j["debug_name"] = json!(debug_name.as_str());
+    }
     j
 }
 
@@ -619,9 +955,21 @@ fn mir_body(
     let mir = ms.mir.unwrap();
     let mut vars = Vec::new();
 
-    vars.push(local_json(ms, mir::RETURN_PLACE));
-    for (_, v) in mir.vars_and_temps_iter().enumerate() {
-        vars.push(local_json(ms, v));
+    // Locals that are never mentioned in a `StorageLive` or `StorageDead` statement, such as the
+    // return place, have storage for the whole body.  Accessing any other local is only valid
+    // between a `StorageLive` and the following `StorageDead`.
+    let storage_locals = mir.basic_blocks.iter()
+        .flat_map(|bb| bb.statements.iter())
+        .filter_map(|stmt| match stmt.kind {
+            mir::StatementKind::StorageLive(l) | mir::StatementKind::StorageDead(l) => Some(l),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+
+    for v in iter::once(mir::RETURN_PLACE).chain(mir.vars_and_temps_iter()) {
+        let mut j = local_json(ms, v);
+        j["always_live"] = json!(!storage_locals.contains(&v));
+        vars.push(j);
     }
 
     let mut blocks = Vec::new();
@@ -633,12 +981,97 @@ fn mir_body(
             }),
         );
     }
-    json!({
+    let mut j = json!({
         "vars": vars,
-        "blocks": blocks
+        "blocks": blocks,
+        // When this is false, the terminators have no unwind edges, and a panic aborts the
+        // program.
+        "unwind": ms.state.tcx.sess.panic_strategy() == spec::PanicStrategy::Unwind,
+    });
+    if ms.opts.source_scopes {
+        j["source_scopes"] = source_scopes_json(ms);
+    }
+    j
+}
+
+/// Describe how the method with signature `sig` takes its `self` argument: `"Value"` for
+/// `self`, `"Ref"` for `&self`, `"RefMut"` for `&mut self`, and `"Custom"` for any other
+/// receiver type, such as `Box<Self>` or `Pin<&mut Self>`.  `ty` is always the receiver type.
+/// Every method in a vtable has a receiver, since a method without one isn't object safe.
+fn receiver_json<'tcx>(
+    ms: &mut MirState<'_, 'tcx>,
+    self_ty: ty::Ty<'tcx>,
+    sig: ty::FnSig<'tcx>,
+) -> serde_json::Value {
+    let recv_ty = match sig.inputs().first() {
+        Some(&ty) => ty,
+        None => return serde_json::Value::Null,
+    };
+    let kind = match *recv_ty.kind() {
+        _ if recv_ty == self_ty => "Value",
+        ty::TyKind::Ref(_, inner, ast::Mutability::Not) if inner == self_ty => "Ref",
+        ty::TyKind::Ref(_, inner, ast::Mutability::Mut) if inner == self_ty => "RefMut",
+        _ => "Custom",
+    };
+    json!({
+        "kind": kind,
+        "ty": recv_ty.to_json(ms),
     })
 }
 
+/// Serialize the source scope tree of the current body.  Each statement and terminator's `scope`
+/// is an index into this table.  Scopes that came from an inlined function record the callee and
+/// the span of the call; `inlined_parent_scope` is the nearest enclosing scope that was inlined
+/// (or `null`), which lets a consumer walk the chain of inlined calls without visiting every
+/// lexical scope.
+fn source_scopes_json(ms: &mut MirState) -> serde_json::Value {
+    let mir = ms.mir.unwrap();
+    let tcx = ms.state.tcx;
+    mir.source_scopes.iter()
+        .map(|scope| {
+            let inlined = scope.inlined.map(|(inst, call_site)| json!({
+                "callee": tcx.def_path_str(inst.def_id()),
+                "call_site": call_site.to_json(ms),
+            }));
+            json!({
+                "parent": scope.parent_scope.map(|s| s.as_usize()),
+                "span": scope.span.to_json(ms),
+                "inlined": inlined,
+                "inlined_parent_scope": scope.inlined_parent_scope.map(|s| s.as_usize()),
+            })
+        })
+        .collect::<Vec<_>>()
+        .into()
+}
+
+
+/// Emit every trait alias defined in the current crate, listing the traits it stands for.  Trait
+/// aliases never appear in monomorphized code (rustc expands them where they're used), so this
+/// is only for consumers that need to resolve aliases in source-level bounds.  Since aliases may
+/// be generic, we give only the component traits' names, not their substs.
+fn emit_trait_aliases(ms: &mut MirState, out: &mut impl JsonOutput) -> io::Result<()> {
+    let tcx = ms.state.tcx;
+    for item_id in tcx.hir().items() {
+        let def_id = item_id.owner_id.to_def_id();
+        if tcx.def_kind(def_id) != DefKind::TraitAlias {
+            continue;
+        }
+        let traits = tcx.super_predicates_of(def_id).predicates.iter()
+            .filter_map(|&(pred, _)| pred.to_opt_poly_trait_pred())
+            .map(|tp| json!({
+                "trait": def_id_str(tcx, tp.def_id()),
+                "trait_name": tcx.def_path_str(tp.def_id()),
+            }))
+            .collect::<Vec<_>>();
+        out.emit(EntryKind::TraitAlias, json!({
+            "name": def_id_str(tcx, def_id),
+            "crate": crate_json(tcx, def_id),
+            "generics": tcx.generics_of(def_id).to_json(ms),
+            "traits": traits,
+        }))?;
+    }
+    emit_new_defs(ms, out)
+}
 
 fn emit_trait<'tcx>(
     ms: &mut MirState<'_, 'tcx>,
@@ -667,28 +1100,91 @@ fn emit_trait<'tcx>(
             "kind": "Method",
             "item_id": def_id.to_json(ms),
             "signature": sig.to_json(ms),
+            "receiver": receiver_json(ms, substs.type_at(0), sig),
         }));
     }
 
+    let supertraits = trait_supertraits(ms, &ti);
+    // The trait's own bounds, such as `T: Clone` in `trait Foo<T: Clone>`, instantiated for the
+    // `dyn` type.  These include the supertrait bounds, and the `Self: Trait` predicate that
+    // rustc adds to every trait.
+    let predicates = ti.concrete_trait_ref(tcx)
+        .map(|tref| predicates_json(ms, tref.def_id, tref.substs));
+
     ms.state.session.note_without_error(&format!("Emitting trait def for {:?}", ti.dyn_ty(tcx)));
+    ms.missing.set_item(&trait_inst_id_str(tcx, &ti));
 
     out.emit(EntryKind::Trait, json!({
         // `name` corresponds to `trait_id` in vtables, Virtual, and Dynamic types.
         "name": trait_inst_id_str(ms.state.tcx, &ti),
+        "crate": ti.trait_ref.map(|tr| crate_json(tcx, tr.def_id)),
         "items": items,
+        "supertraits": supertraits,
+        "predicates": predicates,
+        // Either `"Unsafe"` (for an `unsafe trait`) or `"Normal"`.
+        "unsafety": ti.trait_ref.map(|tr| format!("{:?}", tcx.trait_def(tr.def_id).unsafety)),
     }))?;
     emit_new_defs(ms, out)?;
     Ok(())
 }
 
 
-/// Emit all statics defined in the current crate.
-fn emit_statics(ms: &mut MirState, out: &mut impl JsonOutput) -> io::Result<()> {
+/// Get the names of all the (transitive) supertraits of `ti`, and mark them as used.  Each
+/// supertrait is instantiated with the same `Self` type as `ti`, so its methods have the same
+/// signatures as they do in the vtable of `ti`.
+fn trait_supertraits<'tcx>(
+    ms: &mut MirState<'_, 'tcx>,
+    ti: &TraitInst<'tcx>,
+) -> Vec<String> {
+    let tcx = ms.state.tcx;
+    let tref = match ti.concrete_trait_ref(tcx) {
+        Some(x) => x,
+        None => return Vec::new(),
+    };
+
+    let mut seen = vec![tref];
+    let mut pending = vec![tref];
+    let mut names = Vec::new();
+    while let Some(tref) = pending.pop() {
+        let poly_tref = ty::Binder::dummy(tref);
+        for &(pred, _) in tcx.super_predicates_of(tref.def_id).predicates {
+            let pred = pred.subst_supertrait(tcx, &poly_tref);
+            let super_tref = match tcx.erase_late_bound_regions(pred.kind()) {
+                ty::PredicateKind::Clause(ty::Clause::Trait(tp)) => tp.trait_ref,
+                _ => continue,
+            };
+            if seen.contains(&super_tref) {
+                continue;
+            }
+            seen.push(super_tref);
+            pending.push(super_tref);
+
+            let super_ti = TraitInst::from_trait_ref(tcx, super_tref);
+            names.push(trait_inst_id_str(tcx, &super_ti));
+            ms.used.traits.insert(super_ti);
+        }
+    }
+    names
+}
+
+
+/// Mark all statics defined in the current crate as used, so they will be emitted.
+fn emit_statics(ms: &mut MirState) -> io::Result<()> {
     let (_, cgus) = ms.state.tcx.collect_and_partition_mono_items(());
     for cgu in cgus {
         for mono_item in cgu.items().keys() {
             match *mono_item {
-                MonoItem::Static(def_id) => emit_static(ms, out, def_id)?,
+                MonoItem::Static(def_id) => {
+                    if let Some(ref pat) = ms.opts.filter {
+                        if !def_id_str(ms.state.tcx, def_id).contains(pat.as_str()) {
+                            continue;
+                        }
+                    }
+                    if ms.opts.headers_only && !ms.state.tcx.visibility(def_id).is_public() {
+                        continue;
+                    }
+                    ms.used.statics.insert(def_id);
+                },
                 MonoItem::Fn(_) |
                 MonoItem::GlobalAsm(_) => {},
             }
@@ -701,31 +1197,69 @@ fn emit_static(ms: &mut MirState, out: &mut impl JsonOutput, def_id: DefId) -> i
     let tcx = ms.state.tcx;
     let name = def_id_str(tcx, def_id);
 
+    // Statics declared in an `extern` block have no initializer, so we can only emit their
+    // declarations.  These are reached through constants that point at them, such as reads of
+    // the static and initializers that take its address.
+    if ms.opts.headers_only || tcx.is_foreign_item(def_id) {
+        let ty = tcx.type_of(def_id);
+        let kind = if tcx.is_foreign_item(def_id) { "foreign" } else { "decl" };
+        return emit_static_decl(ms, out, &name, def_id, ty, tcx.is_mutable_static(def_id),
+                                tcx.is_thread_local_static(def_id), kind, None);
+    }
+
     // let mir = tcx.optimized_mir(def_id);
     let mir = tcx.mir_for_ctfe(def_id);
-    emit_fn(ms, out, &name, None, mir)?;
-    emit_static_decl(ms, out, &name, mir.return_ty(), tcx.is_mutable_static(def_id))?;
+    emit_fn(ms, out, &name, def_id, None, mir)?;
+
+    // Also render the evaluated initial value, so consumers can model reads from the static
+    // without running its initializer.  Evaluation can fail (e.g. for statics whose initializer
+    // reads another `static mut`), in which case only the initializer body is available.
+    let ty = mir.return_ty();
+    let init = tcx.eval_static_initializer(def_id).ok().map(|alloc| {
+        let val = interpret::ConstValue::ByRef { alloc, offset: abi::Size::ZERO };
+        render_const_value(ms, val, ty)
+    });
+    emit_static_decl(ms, out, &name, def_id, ty, tcx.is_mutable_static(def_id),
+                     tcx.is_thread_local_static(def_id), "body", init)?;
 
-    for (idx, mir) in tcx.promoted_mir(def_id).iter_enumerated() {
-        emit_promoted(ms, out, &name, idx, mir)?;
+    let inst = ty::Instance::mono(tcx, def_id);
+    for idx in tcx.promoted_mir(def_id).indices() {
+        ms.used.promoteds.insert((inst, idx));
     }
 
     Ok(())
 }
 
-/// Add a new static declaration to `out.statics`.
+/// Add a new static declaration to `out.statics`.  `def_id` is the static, or the item whose body
+/// contains it, for promoteds.  `init` is the rendered initial value of the static, if it could
+/// be evaluated.  `thread_local` is set for `#[thread_local]` statics, which have a separate
+/// instance (and address) in each thread.  `kind` is `"body"` when an initializer fn with the
+/// same name was emitted, `"foreign"` for statics declared in an `extern` block, and `"decl"` for
+/// statics whose initializer was left out (under `--headers-only`).
 fn emit_static_decl<'tcx>(
     ms: &mut MirState<'_, 'tcx>,
     out: &mut impl JsonOutput,
     name: &str,
+    def_id: DefId,
     ty: ty::Ty<'tcx>,
     mutable: bool,
+    thread_local: bool,
+    kind: &str,
+    init: Option<serde_json::Value>,
 ) -> io::Result<()> {
+    ms.missing.set_item(name);
+    // Statics containing an `UnsafeCell` can be mutated through shared references, even when the
+    // static itself is not `static mut`.
+    let interior_mutable = !ms.state.tcx.is_freeze_raw(ty::ParamEnv::reveal_all().and(ty));
     let j = json!({
         "name": name,
+        "crate": crate_json(ms.state.tcx, def_id),
         "ty": ty.to_json(ms),
         "mutable": mutable,
-        "kind": "body",
+        "interior_mutable": interior_mutable,
+        "thread_local": thread_local,
+        "kind": kind,
+        "rendered": init,
     });
     out.emit(EntryKind::Static, j)?;
     emit_new_defs(ms, out)
@@ -759,13 +1293,215 @@ fn init_instances(ms: &mut MirState, out: &mut impl JsonOutput) -> io::Result<()
     let is_top_level = ms.state.session.parse_sess.config.iter()
         .any(|&(key, _)| key.as_str() == "crux_top_level");
 
-    if is_top_level {
+    let opts = ms.opts;
+    if opts.rust_tests {
+        init_instances_from_rust_tests(ms, out)?;
+    }
+    if let Some(ref pat) = opts.filter {
+        init_instances_from_filter(ms, out, pat)
+    } else if opts.headers_only {
+        init_instances_from_public_items(ms, out)
+    } else if is_top_level {
         init_instances_from_tests(ms, out)
+    } else {
+        init_instances_from_crate_type(ms)
+    }
+}
+
+/// Seed `ms.used.instances` for a dependency crate, based on how the crate will be used.  An
+/// executable is entered through `main` and a `cdylib` or `staticlib` through its exported
+/// symbols, so for those we start from the entry points and let the worklist find everything
+/// reachable from them.  Executables can also have exported symbols (such as `#[no_mangle]`
+/// functions called from C, or `#[used]` statics), which are included too.  Any other library
+/// (or a mix of crate types) may have any of its items called from downstream crates, so we
+/// include all of them.
+fn init_instances_from_crate_type(ms: &mut MirState) -> io::Result<()> {
+    let tcx = ms.state.tcx;
+    let crate_types = tcx.sess.crate_types();
+    if crate_types.iter().all(|&ct| ct == CrateType::Executable) {
+        init_instances_from_entry_fn(ms)?;
+        init_instances_from_exported_symbols(ms)?;
+        init_statics_from_used_attr(ms)
+    } else if crate_types.iter()
+            .all(|&ct| matches!(ct, CrateType::Cdylib | CrateType::Staticlib)) {
+        init_instances_from_exported_symbols(ms)
     } else {
         init_instances_from_mono_items(ms)
     }
 }
 
+/// Add the crate's `main` function to `ms.used.instances`.  If the crate has no `main` (for
+/// example, because it's `#![no_main]`), we fall back on including every `MonoItem::Fn`.
+fn init_instances_from_entry_fn(ms: &mut MirState) -> io::Result<()> {
+    let tcx = ms.state.tcx;
+    match tcx.entry_fn(()) {
+        // `main` can't be generic, so it has exactly one instance.
+        Some((def_id, _)) => {
+            ms.used.instances.insert(ty::Instance::mono(tcx, def_id));
+            Ok(())
+        },
+        None => init_instances_from_mono_items(ms),
+    }
+}
+
+/// Add every function and static that the crate exports to `ms.used.instances` or
+/// `ms.used.statics`.
+fn init_instances_from_exported_symbols(ms: &mut MirState) -> io::Result<()> {
+    let tcx = ms.state.tcx;
+    for &(exported, _) in tcx.exported_symbols(LOCAL_CRATE) {
+        match exported {
+            ExportedSymbol::NonGeneric(def_id) => match tcx.def_kind(def_id) {
+                DefKind::Fn | DefKind::AssocFn =>
+                    ms.used.instances.insert(ty::Instance::mono(tcx, def_id)),
+                DefKind::Static(_) => ms.used.statics.insert(def_id),
+                _ => {},
+            },
+            ExportedSymbol::Generic(def_id, substs) =>
+                ms.used.instances.insert(ty::Instance::new(def_id, substs)),
+            ExportedSymbol::DropGlue(ty) =>
+                ms.used.instances.insert(ty::Instance::resolve_drop_in_place(tcx, ty)),
+            // Symbols such as the allocator shims have no MIR of their own.
+            ExportedSymbol::NoDefId(_) => {},
+        }
+    }
+    Ok(())
+}
+
+/// Add every `#[used]` static in the crate to `ms.used.statics`.  These are kept in the binary
+/// even when nothing refers to them, but they aren't necessarily exported.
+fn init_statics_from_used_attr(ms: &mut MirState) -> io::Result<()> {
+    let tcx = ms.state.tcx;
+    for item_id in tcx.hir().items() {
+        let def_id = item_id.owner_id.to_def_id();
+        if !matches!(tcx.def_kind(def_id), DefKind::Static(_)) {
+            continue;
+        }
+        let flags = tcx.codegen_fn_attrs(def_id).flags;
+        if flags.intersects(CodegenFnAttrFlags::USED | CodegenFnAttrFlags::USED_LINKER) {
+            ms.used.statics.insert(def_id);
+        }
+    }
+    Ok(())
+}
+
+/// Add every `MonoItem::Fn` whose path contains `pat` to `ms.used.instances`, and mark it as a
+/// root.  The path is matched against both the plain `def_id_str` of the function and the full
+/// name of the instance, so names can be copied directly from earlier output.
+fn init_instances_from_filter(
+    ms: &mut MirState,
+    out: &mut impl JsonOutput,
+    pat: &str,
+) -> io::Result<()> {
+    let tcx = ms.state.tcx;
+    let (_, cgus) = tcx.collect_and_partition_mono_items(());
+    for cgu in cgus {
+        for mono_item in cgu.items().keys() {
+            let inst = match *mono_item {
+                MonoItem::Fn(inst) => inst,
+                MonoItem::Static(_) |
+                MonoItem::GlobalAsm(_) => continue,
+            };
+            let name = inst_id_str(tcx, inst);
+            if !name.contains(pat) && !def_id_str(tcx, inst.def_id()).contains(pat) {
+                continue;
+            }
+            ms.used.instances.insert(inst);
+            out.add_root(name)?;
+        }
+    }
+    Ok(())
+}
+
+/// Add every `#[test]` function in the crate to `ms.used.instances`, and mark it as a root.
+fn init_instances_from_rust_tests(ms: &mut MirState, out: &mut impl JsonOutput) -> io::Result<()> {
+    let tcx = ms.state.tcx;
+    // Walk the items in source order, so the roots come out in a stable order.
+    for item_id in tcx.hir().items() {
+        let def_id = item_id.owner_id.to_def_id();
+        if !ms.test_fns.contains(&def_id) {
+            continue;
+        }
+        // The harness calls each test with no arguments, so test functions are never generic.
+        let inst = ty::Instance::mono(tcx, def_id);
+        ms.used.instances.insert(inst);
+        out.add_root(inst_id_str(tcx, inst))?;
+    }
+    Ok(())
+}
+
+/// Collect the `#[test]` functions of the local crate.  For each test, the harness generates a
+/// descriptor `const` with the same name as the function, in the same module, and marks it with
+/// `#[rustc_test_marker]`.  We find all the markers first, then match functions against them, so
+/// the crate's items are only scanned twice in total.
+fn rust_test_fns(tcx: TyCtxt) -> HashSet<DefId> {
+    let item_def_ids = || tcx.hir().items().map(|item_id| item_id.owner_id.def_id);
+    let markers = item_def_ids()
+        .filter(|&did| {
+            tcx.def_kind(did) == DefKind::Const &&
+                tcx.has_attr(did.to_def_id(), sym::rustc_test_marker)
+        })
+        .map(|did| (tcx.parent_module_from_def_id(did), tcx.item_name(did.to_def_id())))
+        .collect::<HashSet<_>>();
+    if markers.is_empty() {
+        return HashSet::new();
+    }
+    item_def_ids()
+        .filter(|&did| {
+            tcx.def_kind(did) == DefKind::Fn &&
+                markers.contains(&(tcx.parent_module_from_def_id(did),
+                                   tcx.item_name(did.to_def_id())))
+        })
+        .map(|did| did.to_def_id())
+        .collect()
+}
+
+/// Describe the `#[should_panic]` attribute of a test function, or return `null` if it has none.
+/// `expected` is the substring the panic message must contain, if one was given.
+fn should_panic_json(tcx: TyCtxt, def_id: DefId) -> serde_json::Value {
+    let attr = match tcx.get_attr(def_id, sym::should_panic) {
+        Some(x) => x,
+        None => return serde_json::Value::Null,
+    };
+    // Both `#[should_panic = "msg"]` and `#[should_panic(expected = "msg")]` are accepted.
+    let expected = attr.value_str().or_else(|| {
+        attr.meta_item_list()?.iter()
+            .find(|mi| mi.has_name(sym::expected))?
+            .value_str()
+    });
+    json!({
+        "expected": expected.map(|s| s.as_str().to_owned()),
+    })
+}
+
+/// Add every public `MonoItem::Fn` to `ms.used.instances`, and mark it as a root.  This is used
+/// for `--headers-only`, where no bodies are emitted, so nothing else would be reachable.
+fn init_instances_from_public_items(
+    ms: &mut MirState,
+    out: &mut impl JsonOutput,
+) -> io::Result<()> {
+    let tcx = ms.state.tcx;
+    let (_, cgus) = tcx.collect_and_partition_mono_items(());
+    for cgu in cgus {
+        for mono_item in cgu.items().keys() {
+            let inst = match *mono_item {
+                MonoItem::Fn(inst) => inst,
+                MonoItem::Static(_) |
+                MonoItem::GlobalAsm(_) => continue,
+            };
+            let def_id = match inst.def {
+                ty::InstanceDef::Item(def_id) => def_id.did,
+                _ => continue,
+            };
+            if !tcx.visibility(def_id).is_public() {
+                continue;
+            }
+            ms.used.instances.insert(inst);
+            out.add_root(inst_id_str(tcx, inst))?;
+        }
+    }
+    Ok(())
+}
+
 /// Add every `MonoItem::Fn` to `ms.used.instances`.
 fn init_instances_from_mono_items(ms: &mut MirState) -> io::Result<()> {
     let (_, cgus) = ms.state.tcx.collect_and_partition_mono_items(());
@@ -837,6 +1573,22 @@ fn init_instances_from_tests(ms: &mut MirState, out: &mut impl JsonOutput) -> io
 }
 
 
+/// Check whether `inst` was already translated by the upstream crate that defines it, so that
+/// downstream crates don't need to emit it again.
+fn translated_upstream<'tcx>(tcx: TyCtxt<'tcx>, inst: ty::Instance<'tcx>) -> bool {
+    let def_id = inst.def_id();
+    if def_id.is_local() {
+        return false;
+    }
+    if tcx.is_reachable_non_generic(def_id) {
+        return true;
+    }
+    // Items with upstream monomorphizations have already been translated into an upstream
+    // crate, so we can skip them.
+    tcx.upstream_monomorphizations_for(def_id)
+        .map_or(false, |monos| monos.contains_key(&inst.substs))
+}
+
 /// Add a single `Instance` to `out.fns` and/or `out.intrinsics`, depending on its kind.
 fn emit_instance<'tcx>(
     ms: &mut MirState<'_, 'tcx>,
@@ -846,33 +1598,46 @@ fn emit_instance<'tcx>(
     let tcx = ms.state.tcx;
 
     let name = inst_id_str(tcx, inst);
+    ms.missing.set_item(&name);
 
     // We actually record every instance in `intrinsics`, not just `InstanceDef::Intrinsic` and
     // other special functions, because the intrinsics table is used to look up CustomOps.
     // (CustomOps are keyed on the pre-monomorphization name of the function.)
     out.emit(EntryKind::Intrinsic, json!({
         "name": &name,
+        "crate": crate_json(tcx, inst.def_id()),
         "inst": inst.to_json(ms),
     }))?;
     emit_new_defs(ms, out)?;
 
+    if ms.opts.headers_only {
+        // Shims have no signature of their own in the source, so they're omitted entirely.
+        return match inst.def {
+            ty::InstanceDef::Item(_) if inst.ty(tcx, ty::ParamEnv::reveal_all()).is_fn() =>
+                emit_fn_decl(ms, out, &name, inst),
+            _ => Ok(()),
+        };
+    }
+
     match inst.def {
         ty::InstanceDef::Item(def_id) => {
             // Foreign items and non-generics have no MIR available.
             let def_id = def_id.did;
             if tcx.is_foreign_item(def_id) {
+                // Foreign items have no body, but with `--extern-fns` we emit a declaration
+                // carrying the symbol name, so consumers can match it with its external
+                // definition.
+                if ms.opts.extern_fns {
+                    return emit_fn_decl(ms, out, &name, inst);
+                }
                 return Ok(());
             }
-            if !def_id.is_local() {
-                if tcx.is_reachable_non_generic(def_id) {
-                    return Ok(());
-                }
-                // Items with upstream monomorphizations have already been translated into an upstream
-                // crate, so we can skip them.
-                if tcx.upstream_monomorphizations_for(def_id)
-                        .map_or(false, |monos| monos.contains_key(&inst.substs)) {
-                    return Ok(());
-                }
+            if ms.opts.opaque_crates.contains(tcx.crate_name(def_id.krate).as_str()) &&
+                    inst.ty(tcx, ty::ParamEnv::reveal_all()).is_fn() {
+                return emit_fn_decl(ms, out, &name, inst);
+            }
+            if translated_upstream(tcx, inst) {
+                return Ok(());
             }
         },
         // These variants are unsupported by the `mir_shims` query, which backs `instance_mir`.
@@ -886,31 +1651,37 @@ fn emit_instance<'tcx>(
     let mir: Body = tcx.subst_and_normalize_erasing_regions(
         inst.substs, ty::ParamEnv::reveal_all(), mir.clone());
     let mir = tcx.arena.alloc(mir);
-    emit_fn(ms, out, &name, Some(inst), mir)?;
+    emit_fn(ms, out, &name, inst.def_id(), Some(inst), mir)?;
 
     if let ty::InstanceDef::Item(def_id) = inst.def {
-        let def_id = def_id.did;
-        for (idx, mir) in tcx.promoted_mir(def_id).iter_enumerated() {
-            let mir = tcx.subst_and_normalize_erasing_regions(
-                inst.substs, ty::ParamEnv::reveal_all(), mir.clone());
-            let mir = tcx.arena.alloc(mir);
-            emit_promoted(ms, out, &name, idx, mir)?;
+        for idx in tcx.promoted_mir(def_id.did).indices() {
+            ms.used.promoteds.insert((inst, idx));
         }
     }
 
     Ok(())
 }
 
+/// Emit promoted `idx` of the body of `inst`, which must be an `InstanceDef::Item`.
 fn emit_promoted<'tcx>(
     ms: &mut MirState<'_, 'tcx>,
     out: &mut impl JsonOutput,
-    parent: &str,
+    inst: ty::Instance<'tcx>,
     idx: mir::Promoted,
-    mir: &'tcx Body<'tcx>,
 ) -> io::Result<()> {
-    let name = format!("{}::{{{{promoted}}}}[{}]", parent, idx.as_usize());
-    emit_fn(ms, out, &name, None, mir)?;
-    emit_static_decl(ms, out, &name, mir.return_ty(), false)?;
+    let tcx = ms.state.tcx;
+    // The crate that translated `inst` also emitted the promoteds its body refers to.
+    if translated_upstream(tcx, inst) {
+        return Ok(());
+    }
+    let mir = &tcx.promoted_mir(inst.def_id())[idx];
+    let mir = tcx.subst_and_normalize_erasing_regions(
+        inst.substs, ty::ParamEnv::reveal_all(), mir.clone());
+    let mir = tcx.arena.alloc(mir);
+    let name = promoted_name(&inst_id_str(tcx, inst), idx);
+    emit_fn(ms, out, &name, inst.def_id(), None, mir)?;
+    emit_static_decl(ms, out, &name, inst.def_id(), mir.return_ty(), false, false, "body",
+                     None)?;
     Ok(())
 }
 
@@ -918,23 +1689,58 @@ fn emit_promoted<'tcx>(
 fn emit_vtable<'tcx>(
     ms: &mut MirState<'_, 'tcx>,
     out: &mut impl JsonOutput,
-    poly_trait_ref: ty::PolyTraitRef<'tcx>,
+    desc: (ty::PolyTraitRef<'tcx>, Vec<DefId>),
 ) -> io::Result<()> {
-    let trait_ref = ms.state.tcx.erase_late_bound_regions(poly_trait_ref);
-    let ti = TraitInst::from_trait_ref(ms.state.tcx, trait_ref);
+    let tcx = ms.state.tcx;
+    let poly_trait_ref = desc.0;
+    let trait_ref = tcx.erase_late_bound_regions(poly_trait_ref);
+    let ti = vtable_trait_inst(tcx, &desc);
+    let name = vtable_name(ms, &desc);
+    ms.missing.set_item(&name);
+    // The first three slots of every vtable hold the drop glue, size, and alignment of the
+    // concrete type.  If the layout can't be computed, we emit `null` for the size and alignment
+    // rather than giving up on the whole crate.
+    let self_ty = trait_ref.self_ty();
+    let layout = tcx.layout_of(ty::ParamEnv::reveal_all().and(self_ty)).ok();
     out.emit(EntryKind::Vtable, json!({
-        "trait_id": trait_inst_id_str(ms.state.tcx, &ti),
-        "name": vtable_name(ms, poly_trait_ref),
+        "trait_id": trait_inst_id_str(tcx, &ti),
+        "name": name,
+        "crate": crate_json(tcx, poly_trait_ref.def_id()),
         "items": build_vtable_items(ms, poly_trait_ref),
+        "drop": get_drop_fn_name(ms, self_ty),
+        "size": layout.map(|l| l.size.bytes()),
+        "align": layout.map(|l| l.align.abi.bytes()),
     }))?;
     emit_new_defs(ms, out)
 }
 
+/// Get the `TraitInst` of the `dyn` type that the vtable `desc` is used for.  The `Self` type of
+/// the trait ref is concrete, so the auto traits come from the `dyn` type instead.  Vtables for
+/// `dyn Trait` and `dyn Trait + Send` have the same contents, since auto traits have no methods,
+/// but distinct `trait_id`s.
+fn vtable_trait_inst<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    desc: &(ty::PolyTraitRef<'tcx>, Vec<DefId>),
+) -> TraitInst<'tcx> {
+    let mut ti = TraitInst::from_trait_ref(tcx, tcx.erase_late_bound_regions(desc.0));
+    ti.auto_traits = desc.1.clone();
+    ti
+}
+
 fn vtable_name<'tcx>(
     mir: &mut MirState<'_, 'tcx>,
-    trait_ref: ty::PolyTraitRef<'tcx>,
+    desc: &(ty::PolyTraitRef<'tcx>, Vec<DefId>),
 ) -> String {
-    ext_def_id_str(mir.state.tcx, trait_ref.def_id(), "_vtbl", trait_ref)
+    let tcx = mir.state.tcx;
+    let trait_ref = desc.0;
+    if desc.1.is_empty() {
+        return ext_def_id_str(tcx, trait_ref.def_id(), "_vtbl", trait_ref);
+    }
+    // Include the `dyn` type in the hash, so vtables that differ only in their auto traits get
+    // distinct names.
+    let dyn_ty = vtable_trait_inst(tcx, desc).dyn_ty(tcx)
+        .expect("vtables always have a principal trait");
+    ext_def_id_str(tcx, trait_ref.def_id(), "_vtbl", (trait_ref, dyn_ty))
 }
 
 fn build_vtable_items<'tcx>(
@@ -972,21 +1778,31 @@ fn emit_adt<'tcx>(
     let adt_name = adt_inst_id_str(tcx, ai);
     tcx.sess.note_without_error(
         format!("Emitting ADT definition for {}", adt_name).as_str());
+    ms.missing.set_item(&adt_name);
     out.emit(EntryKind::Adt, ai.to_json(ms))?;
     emit_new_defs(ms, out)?;
     Ok(())
 }
 
 
-/// Output a MIR body to `out.fns`.  Recursively emits all promoted statics from the body.
+/// Output a MIR body to `out.fns`.  `def_id` is the item the body belongs to, which is also the
+/// parent item for promoteds.
 fn emit_fn<'tcx>(
     ms: &mut MirState<'_, 'tcx>,
     out: &mut impl JsonOutput,
     name: &str,
+    def_id: DefId,
     inst: Option<ty::Instance<'tcx>>,
     mir: &'tcx Body<'tcx>,
 ) -> io::Result<()> {
     ms.state.session.note_without_error(&format!("Emitting MIR for {}", name));
+    ms.missing.set_item(name);
+
+    let mir = if ms.opts.split_critical_edges {
+        split_critical_edges(ms.state.tcx, mir)
+    } else {
+        mir
+    };
 
     let mut ms = MirState {
         mir: Some(mir),
@@ -994,21 +1810,253 @@ fn emit_fn<'tcx>(
         state: ms.state,
         tys: ms.tys,
         match_span_map: ms.match_span_map,
+        test_fns: ms.test_fns,
         allocs: ms.allocs,
         export_style: ms.export_style,
+        opts: ms.opts,
+        missing: ms.missing,
+        diags: ms.diags,
     };
     let ms = &mut ms;
 
     let abi = inst.map(|i| inst_abi(ms.state.tcx, i)).unwrap_or(spec::abi::Abi::Rust);
+    let (export_name, linkage) = inst.map(|i| inst_linkage(ms.state.tcx, i))
+        .unwrap_or((None, "internal".to_owned()));
 
-    out.emit(EntryKind::Fn, json!({
+    let mut j = json!({
         "name": &name,
+        "crate": crate_json(ms.state.tcx, def_id),
         "args": mir.args_iter().map(|l| local_json(ms, l)).collect::<Vec<_>>(),
         "return_ty": mir.return_ty().to_json(ms),
         "body": mir_body(ms),
         "abi": abi.to_json(ms),
         "spread_arg": mir.spread_arg.map(|x| x.as_usize()),
-    }))?;
+        "export_name": export_name,
+        "linkage": linkage,
+        "is_const": inst.map_or(false, |i| ms.state.tcx.is_const_fn_raw(i.def_id())),
+        // `#[track_caller]` functions take an implicit extra `&Location` argument, which callers
+        // must supply.
+        "track_caller": inst.map_or(false, |i| i.def.requires_caller_location(ms.state.tcx)),
+        "inline": inst.and_then(|i| inst_inline_hint(ms.state.tcx, i)),
+        "unsafe_impl": inst.map_or(false, |i| inst_unsafe_impl(ms.state.tcx, i)),
+    });
+    if ms.state.tcx.is_closure(def_id) {
+        j["upvars"] = closure_upvars_json(ms);
+    }
+    let impl_self_ty = inst.and_then(|i| inherent_impl_self_ty(ms.state.tcx, i));
+    j["impl_self_ty"] = impl_self_ty.map(|ty| ty.to_json(ms)).into();
+    if ms.opts.instance_predicates {
+        j["predicates"] = json!(inst.map(|i| inst_predicates_json(ms, i)));
+    }
+    if ms.opts.variances {
+        j["variances"] = variances_json(ms.state.tcx, def_id);
+    }
+    if ms.opts.rust_tests {
+        let tcx = ms.state.tcx;
+        let test_def_id = inst
+            .filter(|i| matches!(i.def, ty::InstanceDef::Item(_)))
+            .map(|i| i.def_id())
+            .filter(|did| ms.test_fns.contains(did));
+        j["test_entry"] = test_def_id.is_some().into();
+        j["should_panic"] = test_def_id.map_or(serde_json::Value::Null,
+                                               |did| should_panic_json(tcx, did));
+    }
+    if let Some(ref dir) = ms.opts.dump_mir_cfg {
+        write_cfg_dot(dir, name, mir, &j["body"])?;
+    }
+    out.emit(EntryKind::Fn, j)?;
+    emit_new_defs(ms, out)
+}
+
+/// List the captured variables of the current closure or generator body.  Captures are fields of
+/// the body's first argument (`_1.0`, `_1.1`, ...), possibly behind a reference to the closure,
+/// and rustc records their names in the body's variable debug info.  `by_ref` is set for
+/// captures that hold a reference to the variable rather than the variable itself.
+fn closure_upvars_json(ms: &mut MirState) -> serde_json::Value {
+    let mir = ms.mir.unwrap();
+    let closure_arg = mir::Local::new(1);
+    let mut upvars = Vec::new();
+    for vdi in &mir.var_debug_info {
+        let place = match vdi.value {
+            mir::VarDebugInfoContents::Place(p) if p.local == closure_arg => p,
+            _ => continue,
+        };
+        let proj = place.projection.as_ref();
+        let proj = match proj.first() {
+            Some(mir::ProjectionElem::Deref) => &proj[1..],
+            _ => proj,
+        };
+        let (field, fty, by_ref) = match *proj {
+            [mir::ProjectionElem::Field(f, fty)] => (f, fty, false),
+            [mir::ProjectionElem::Field(f, fty), mir::ProjectionElem::Deref] => (f, fty, true),
+            _ => continue,
+        };
+        upvars.push(json!({
+            "field": field.index(),
+            "name": vdi.name.as_str(),
+            "ty": fty.to_json(ms),
+            "by_ref": by_ref,
+        }));
+    }
+    upvars.into()
+}
+
+/// Write the control-flow graph of `mir` to a Graphviz file in `dir`, for debugging.  Each node
+/// lists the block's statements by their index and `kind` in `body_json` (the serialized body),
+/// so that they line up with the JSON output, and each edge is labeled with the kind of the
+/// terminator it comes from.
+fn write_cfg_dot(
+    dir: &Path,
+    name: &str,
+    mir: &Body,
+    body_json: &serde_json::Value,
+) -> io::Result<()> {
+    use std::io::Write;
+    fn escape(s: &str) -> String {
+        s.replace('\\', "\\\\").replace('"', "\\\"")
+    }
+
+    // Different names can sanitize to the same string (such as `a::b` and `a_b`), so we add a
+    // hash of the full name to keep the files distinct.  The hash must not change between
+    // toolchains, so that dumps from different runs can be compared by file name.
+    let sanitized = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    let file_name = format!("{}-{:016x}.dot", sanitized, fnv1a_hash(name.as_bytes()));
+    let mut f = io::BufWriter::new(File::create(dir.join(file_name))?);
+    writeln!(f, "digraph \"{}\" {{", escape(name))?;
+    writeln!(f, "    node [shape=box, fontname=monospace];")?;
+    let blocks_json = body_json["blocks"].as_array().unwrap();
+    for (bb, block_json) in mir.basic_blocks.indices().zip(blocks_json) {
+        let block_json = &block_json["block"];
+        let mut label = format!("{:?}\\l", bb);
+        for (i, stmt) in block_json["data"].as_array().unwrap().iter().enumerate() {
+            let stmt_kind = escape(stmt["kind"].as_str().unwrap_or("?"));
+            label.push_str(&format!("{}: {}\\l", i, stmt_kind));
+        }
+        let term_kind = escape(block_json["terminator"]["kind"].as_str().unwrap_or("?"));
+        label.push_str(&format!("{}\\l", term_kind));
+        writeln!(f, "    {:?} [label=\"{}\"];", bb, label)?;
+
+        let term = mir[bb].terminator();
+        if let mir::TerminatorKind::SwitchInt { ref targets, .. } = term.kind {
+            for (val, target) in targets.iter() {
+                writeln!(f, "    {:?} -> {:?} [label=\"{}: {}\"];", bb, target, term_kind, val)?;
+            }
+            writeln!(f, "    {:?} -> {:?} [label=\"{}: otherwise\"];",
+                     bb, targets.otherwise(), term_kind)?;
+            continue;
+        }
+        let unwind = term.kind.unwind().and_then(|u| *u);
+        for target in term.successors() {
+            if Some(target) == unwind {
+                writeln!(f, "    {:?} -> {:?} [label=\"{} (unwind)\", style=dashed];",
+                         bb, target, term_kind)?;
+            } else {
+                writeln!(f, "    {:?} -> {:?} [label=\"{}\"];", bb, target, term_kind)?;
+            }
+        }
+    }
+    writeln!(f, "}}")?;
+    Ok(())
+}
+
+/// Serialize the `where` clauses of the item behind `inst`, instantiated with the substs of
+/// `inst`.  These are the obligations that were discharged to monomorphize it.
+fn inst_predicates_json<'tcx>(
+    ms: &mut MirState<'_, 'tcx>,
+    inst: ty::Instance<'tcx>,
+) -> serde_json::Value {
+    predicates_json(ms, inst.def_id(), inst.substs)
+}
+
+/// Serialize the `where` clauses of `def_id` (including those inherited from its parent),
+/// instantiated with `substs`.
+fn predicates_json<'tcx>(
+    ms: &mut MirState<'_, 'tcx>,
+    def_id: DefId,
+    substs: ty::subst::SubstsRef<'tcx>,
+) -> serde_json::Value {
+    let tcx = ms.state.tcx;
+    let preds = tcx.predicates_of(def_id).instantiate(tcx, substs);
+    preds.predicates.iter()
+        .map(|&p| tcx.normalize_erasing_regions(ty::ParamEnv::reveal_all(), p).to_json(ms))
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// Split every critical edge in `mir` (an edge from a block with several successors to a block
+/// with several predecessors) by routing it through a new block containing only a `Goto`.
+/// Returns `mir` itself if it has no critical edges.
+fn split_critical_edges<'tcx>(tcx: TyCtxt<'tcx>, mir: &'tcx Body<'tcx>) -> &'tcx Body<'tcx> {
+    let preds = mir.basic_blocks.predecessors();
+    let mut body = mir.clone();
+    let mut changed = false;
+    for bb in mir.basic_blocks.indices() {
+        let term = mir[bb].terminator();
+        if term.successors().count() < 2 {
+            continue;
+        }
+
+        let first_new = body.basic_blocks.len();
+        let mut new_blocks = Vec::new();
+        for target in body.basic_blocks_mut()[bb].terminator_mut().successors_mut() {
+            if preds[*target].len() < 2 {
+                continue;
+            }
+            let mut data = mir::BasicBlockData::new(Some(mir::Terminator {
+                source_info: term.source_info,
+                kind: mir::TerminatorKind::Goto { target: *target },
+            }));
+            // Edges into cleanup blocks are unwind edges, and must stay within cleanup code.
+            data.is_cleanup = mir[*target].is_cleanup;
+            *target = mir::BasicBlock::new(first_new + new_blocks.len());
+            new_blocks.push(data);
+        }
+
+        changed |= !new_blocks.is_empty();
+        body.basic_blocks_mut().extend(new_blocks);
+    }
+
+    if !changed {
+        return mir;
+    }
+    tcx.arena.alloc(body)
+}
+
+/// Output a declaration with no MIR body to `out.fns`.  This is used for functions in crates
+/// listed in `Options::opaque_crates`, whose definitions are supplied by the consumer instead.
+fn emit_fn_decl<'tcx>(
+    ms: &mut MirState<'_, 'tcx>,
+    out: &mut impl JsonOutput,
+    name: &str,
+    inst: ty::Instance<'tcx>,
+) -> io::Result<()> {
+    let tcx = ms.state.tcx;
+    ms.state.session.note_without_error(&format!("Emitting declaration for {}", name));
+    ms.missing.set_item(name);
+
+    let sig = inst.ty(tcx, ty::ParamEnv::reveal_all()).fn_sig(tcx);
+    let (export_name, linkage) = inst_linkage(tcx, inst);
+    let mut j = json!({
+        "name": &name,
+        "crate": crate_json(tcx, inst.def_id()),
+        "signature": sig.to_json(ms),
+        "generics": tcx.generics_of(inst.def_id()).to_json(ms),
+        "abi": inst_abi(tcx, inst).to_json(ms),
+        "export_name": export_name,
+        "linkage": linkage,
+        "is_const": tcx.is_const_fn_raw(inst.def_id()),
+        "track_caller": inst.def.requires_caller_location(tcx),
+        "inline": inst_inline_hint(tcx, inst),
+        "unsafe_impl": inst_unsafe_impl(tcx, inst),
+        "opaque": true,
+    });
+    j["impl_self_ty"] = inherent_impl_self_ty(tcx, inst).map(|ty| ty.to_json(ms)).into();
+    if ms.opts.variances {
+        j["variances"] = variances_json(tcx, inst.def_id());
+    }
+    out.emit(EntryKind::Fn, j)?;
     emit_new_defs(ms, out)
 }
 
@@ -1022,6 +2070,12 @@ fn emit_new_defs(
     for j in ms.allocs.take_new_allocs() {
         out.emit(EntryKind::Static, j)?;
     }
+    for def_id in ms.used.def_ids.take_new() {
+        out.emit(EntryKind::DefIdName, json!({
+            "name": def_id_str(ms.state.tcx, def_id),
+            "path": ms.state.tcx.def_path_str(def_id),
+        }))?;
+    }
     assert!(ms.tys.take_new_types().is_empty());
     Ok(())
 }
@@ -1048,6 +2102,132 @@ fn inst_abi<'tcx>(
 }
 
 
+/// Print the constructs recorded in `missing`, one line per construct per item.
+fn report_missing(sess: &Session, missing: &MissingReport) {
+    if missing.entries().is_empty() {
+        sess.note_without_error("list-missing: all items translated without fallbacks");
+        return;
+    }
+    for (item, whats) in missing.entries() {
+        for what in whats {
+            sess.note_without_error(&format!("list-missing: {} in {}", what, item));
+        }
+    }
+}
+
+
+/// Get the real symbol name of `inst`, if it has a stable one, along with a linkage hint.  The
+/// symbol name comes from `#[no_mangle]` or `#[export_name]` on definitions, and from the item
+/// name (or `#[link_name]`) for foreign items.  The linkage is one of:
+///
+/// * `"import"`: a foreign item, defined elsewhere.
+/// * `"extern_weak"`: a foreign item that may be missing, with `#[linkage = "extern_weak"]`.
+/// * `"export"`: a definition with an unmangled symbol name.
+/// * `"weak"`: a definition that may be overridden by another of the same name, with
+///   `#[linkage = "weak"]` or similar.
+/// * `"internal"`: any other definition.
+fn inst_linkage<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    inst: ty::Instance<'tcx>,
+) -> (Option<String>, String) {
+    let def_id = match inst.def {
+        ty::InstanceDef::Item(def_id) => def_id.did,
+        _ => return (None, "internal".to_owned()),
+    };
+    let attrs = tcx.codegen_fn_attrs(def_id);
+
+    let (export_name, linkage) = if tcx.is_foreign_item(def_id) {
+        (Some(attrs.link_name.unwrap_or_else(|| tcx.item_name(def_id))), "import")
+    } else if attrs.flags.contains(CodegenFnAttrFlags::NO_MANGLE) {
+        (Some(tcx.item_name(def_id)), "export")
+    } else if let Some(name) = attrs.export_name {
+        (Some(name), "export")
+    } else {
+        (None, "internal")
+    };
+
+    // Map an explicit `#[linkage]` attribute onto the same set of values.
+    let linkage = match attrs.linkage {
+        None => linkage,
+        Some(Linkage::External) | Some(Linkage::Appending) => "export",
+        Some(Linkage::AvailableExternally) => "import",
+        Some(Linkage::ExternalWeak) => "extern_weak",
+        Some(Linkage::LinkOnceAny) | Some(Linkage::LinkOnceODR) | Some(Linkage::WeakAny) |
+        Some(Linkage::WeakODR) | Some(Linkage::Common) => "weak",
+        Some(Linkage::Internal) | Some(Linkage::Private) => "internal",
+    };
+    (export_name.map(|s| s.as_str().to_owned()), linkage.to_owned())
+}
+
+/// Get the `#[inline]` hint of `inst` (`"Hint"`, `"Always"`, or `"Never"`), if it has one.  Unlike
+/// `#[track_caller]`, these hints don't affect the function's behavior.
+fn inst_inline_hint<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    inst: ty::Instance<'tcx>,
+) -> Option<String> {
+    let def_id = match inst.def {
+        ty::InstanceDef::Item(def_id) => def_id.did,
+        _ => return None,
+    };
+    match tcx.codegen_fn_attrs(def_id).inline {
+        rustc_attr::InlineAttr::None => None,
+        inline => Some(format!("{:?}", inline)),
+    }
+}
+
+/// If `inst` is a method of an inherent impl (`impl Foo { ... }`), get the `Self` type of the
+/// impl, instantiated with the substs of `inst`.  (Methods of trait impls are instead linked to
+/// their trait through the vtables and `Virtual` instances that use them.)
+fn inherent_impl_self_ty<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    inst: ty::Instance<'tcx>,
+) -> Option<ty::Ty<'tcx>> {
+    let def_id = match inst.def {
+        ty::InstanceDef::Item(def_id) => def_id.did,
+        _ => return None,
+    };
+    let impl_def_id = tcx.impl_of_method(def_id)?;
+    if tcx.trait_id_of_impl(impl_def_id).is_some() {
+        return None;
+    }
+    // The impl's generic params come first in the method's substs.
+    Some(tcx.subst_and_normalize_erasing_regions(
+        inst.substs, ty::ParamEnv::reveal_all(), tcx.type_of(impl_def_id)))
+}
+
+/// Check whether `inst` is a method of an `unsafe impl`.  The impl's own unsafety is only
+/// available from the HIR, so for other crates we reconstruct it: an impl is unsafe when its
+/// trait is, or when it is a `#[may_dangle]` impl of `Drop` (such as `alloc`'s impl for
+/// `Vec<T>`).  `#[may_dangle]` is recorded in the crate metadata as `pure_wrt_drop` on the
+/// impl's generic params.
+fn inst_unsafe_impl<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    inst: ty::Instance<'tcx>,
+) -> bool {
+    let def_id = match inst.def {
+        ty::InstanceDef::Item(def_id) => def_id.did,
+        _ => return false,
+    };
+    let impl_def_id = match tcx.impl_of_method(def_id) {
+        Some(x) => x,
+        None => return false,
+    };
+    if let Some(local_id) = impl_def_id.as_local() {
+        match tcx.hir().expect_item(local_id).kind {
+            rustc_hir::ItemKind::Impl(ref imp) =>
+                return imp.unsafety == rustc_hir::Unsafety::Unsafe,
+            _ => return false,
+        }
+    }
+    match tcx.trait_id_of_impl(impl_def_id) {
+        Some(trait_id) =>
+            tcx.trait_def(trait_id).unsafety == rustc_hir::Unsafety::Unsafe ||
+                tcx.generics_of(impl_def_id).params.iter().any(|p| p.pure_wrt_drop),
+        None => false,
+    }
+}
+
+
 #[derive(Debug)]
 pub struct AnalysisData<O> {
     pub mir_path: PathBuf,
@@ -1055,6 +2235,147 @@ pub struct AnalysisData<O> {
     pub output: O,
 }
 
+/// The fingerprint file kept for each crate in `--incremental` mode.  It records the crate hash
+/// (which covers upstream crates too) along with the flags that affect the output, and the
+/// mir-json version and schema version, so that upgrading mir-json invalidates old output.
+struct Fingerprint {
+    path: PathBuf,
+    contents: String,
+}
+
+impl Fingerprint {
+    fn new(
+        tcx: TyCtxt,
+        dir: &Path,
+        export_style: ExportStyle,
+        opts: &Options,
+    ) -> Fingerprint {
+        let file_name = format!(
+            "{}-{:016x}.fingerprint",
+            tcx.crate_name(LOCAL_CRATE),
+            tcx.sess.local_stable_crate_id().to_u64(),
+        );
+        let contents = format!(
+            "{}\n{:?}\n{}\nmir-json {} schema {}\n",
+            tcx.crate_hash(LOCAL_CRATE),
+            export_style,
+            opts.flags.join(" "),
+            env!("CARGO_PKG_VERSION"),
+            SCHEMA_VERSION,
+        );
+        Fingerprint { path: dir.join(file_name), contents }
+    }
+
+    /// Check whether `mir_path` was produced from the same crate contents and flags.
+    fn is_current(&self, mir_path: &Path) -> bool {
+        mir_path.exists() &&
+            fs::read_to_string(&self.path).map_or(false, |s| s == self.contents)
+    }
+
+    fn write(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, &self.contents)
+    }
+}
+
+/// Describe the global allocator, if this crate selects one (only crates that are linked into a
+/// final artifact, such as executables, do).  The allocator shims `__rust_alloc`,
+/// `__rust_dealloc`, `__rust_realloc`, and `__rust_alloc_zeroed` have no MIR, since rustc generates
+/// them during codegen.  Each one forwards to a function generated by `#[global_allocator]`
+/// (`__rg_alloc` and so on) or, if there is none, to the default allocator in `std`
+/// (`__rdl_alloc` and so on), and we give the names of those functions here.
+fn allocator_json(ms: &mut MirState) -> Option<serde_json::Value> {
+    let tcx = ms.state.tcx;
+    let kind = tcx.allocator_kind(())?;
+    let prefix = match kind {
+        AllocatorKind::Global => "__rg_",
+        AllocatorKind::Default => "__rdl_",
+    };
+    let mut j = json!({
+        "kind": format!("{:?}", kind),
+    });
+    for method in ["alloc", "dealloc", "realloc", "alloc_zeroed"] {
+        let name = Symbol::intern(&format!("{}{}", prefix, method));
+        j[method] = match find_allocator_fn(tcx, name) {
+            Some(def_id) => {
+                let inst = ty::Instance::mono(tcx, def_id);
+                ms.used.instances.insert(inst);
+                inst_id_str(tcx, inst).into()
+            },
+            None => serde_json::Value::Null,
+        };
+    }
+    Some(j)
+}
+
+/// Find the allocator function named `name`, either in the current crate or among the symbols
+/// exported by its dependencies.
+fn find_allocator_fn(tcx: TyCtxt, name: Symbol) -> Option<DefId> {
+    let is_match = |def_id: DefId| {
+        tcx.def_kind(def_id) == DefKind::Fn && tcx.opt_item_name(def_id) == Some(name)
+    };
+    let local = tcx.mir_keys(()).iter()
+        .map(|local_def_id| local_def_id.to_def_id())
+        .find(|&def_id| is_match(def_id));
+    if local.is_some() {
+        return local;
+    }
+    tcx.crates(()).iter()
+        .flat_map(|&cnum| tcx.exported_symbols(cnum).iter())
+        .find_map(|&(exported, _)| match exported {
+            ExportedSymbol::NonGeneric(def_id) if is_match(def_id) => Some(def_id),
+            _ => None,
+        })
+}
+
+/// Describe the target the crate is compiled for.  Layouts and rendered constants in the output
+/// depend on these properties.
+fn target_json(tcx: TyCtxt) -> serde_json::Value {
+    let dl = &tcx.data_layout;
+    json!({
+        "triple": tcx.sess.opts.target_triple.triple(),
+        "endian": dl.endian.as_str(),
+        "pointer_size": dl.pointer_size.bytes(),
+        "pointer_align": dl.pointer_align.abi.bytes(),
+        "i8_align": dl.i8_align.abi.bytes(),
+        "i16_align": dl.i16_align.abi.bytes(),
+        "i32_align": dl.i32_align.abi.bytes(),
+        "i64_align": dl.i64_align.abi.bytes(),
+        "i128_align": dl.i128_align.abi.bytes(),
+        "f32_align": dl.f32_align.abi.bytes(),
+        "f64_align": dl.f64_align.abi.bytes(),
+        "aggregate_align": dl.aggregate_align.abi.bytes(),
+    })
+}
+
+/// Write the names of everything in `used` to `path`, for `--emit-deps`.  Since `used` records
+/// every item reachable from the crate's roots, this is the crate's full monomorphic dependency
+/// set.  Each list is sorted, so the output is reproducible.
+fn write_deps<'tcx>(tcx: TyCtxt<'tcx>, used: &Used<'tcx>, path: &Path) -> io::Result<()> {
+    fn sorted(it: impl Iterator<Item = String>) -> Vec<String> {
+        let mut v = it.collect::<Vec<_>>();
+        v.sort();
+        v.dedup();
+        v
+    }
+    let j = json!({
+        "instances": sorted(used.instances.iter().map(|&inst| inst_id_str(tcx, inst))),
+        "types": sorted(used.types.iter().map(|&ai| adt_inst_id_str(tcx, ai))),
+        "traits": sorted(used.traits.iter().map(|ti| trait_inst_id_str(tcx, ti))),
+    });
+    serde_json::to_writer_pretty(File::create(path)?, &j)?;
+    Ok(())
+}
+
+/// The output of `analyze_inner`.  `out` is `None` if the existing `.mir` file was up to date, in
+/// which case nothing new was written.
+struct InnerOutput<O> {
+    out: Option<O>,
+    fingerprint: Option<Fingerprint>,
+}
+
 /// Analyze the crate currently being compiled.  Returns `Ok(Some(data))` upon successfully writing
 /// the crate MIR, returns `Ok(None)` when there is no need to write out MIR (namely, when `comp`
 /// is not producing an `Exe` output), and returns `Err(e)` on I/O or serialization errors.
@@ -1062,11 +2383,14 @@ fn analyze_inner<'tcx, O: JsonOutput, F: FnOnce(&Path) -> io::Result<O>>(
     sess: &Session,
     queries: &'tcx Queries<'tcx>,
     export_style: ExportStyle,
+    opts: &Options,
     mk_output: F,
-) -> Result<Option<AnalysisData<O>>, serde_cbor::Error> {
+) -> Result<Option<AnalysisData<InnerOutput<O>>>, serde_cbor::Error> {
     let mut mir_path = None;
     let mut extern_mir_paths = Vec::new();
 
+    set_stable_names(opts.stable_names);
+    set_target_schema(opts.schema());
     let output = queries.global_ctxt().unwrap().enter(|tcx| -> io::Result<_> {
         let outputs = tcx.output_filenames(());
         if !outputs.outputs.contains_key(&OutputType::Exe) {
@@ -1078,8 +2402,6 @@ fn analyze_inner<'tcx, O: JsonOutput, F: FnOnce(&Path) -> io::Result<O>>(
             &outputs,
             tcx.crate_name(LOCAL_CRATE),
         ).with_extension("mir");
-        let mut out = mk_output(&mir_path_)?;
-        mir_path = Some(mir_path_);
 
         for &cnum in tcx.crates(()) {
             let src = tcx.used_crate_source(cnum);
@@ -1097,30 +2419,66 @@ fn analyze_inner<'tcx, O: JsonOutput, F: FnOnce(&Path) -> io::Result<O>>(
             }
         }
 
+        // `--list-missing` writes no output, so an up-to-date output says nothing about what
+        // would be reported.
+        let fingerprint = opts.incremental.as_ref()
+            .filter(|_| !opts.list_missing)
+            .map(|dir| Fingerprint::new(tcx, dir, export_style, opts));
+        if let Some(ref fp) = fingerprint {
+            let out_path = if opts.json_lines {
+                mir_path_.with_extension("jsonl")
+            } else {
+                mir_path_.clone()
+            };
+            if fp.is_current(&out_path) {
+                sess.note_without_error(
+                    format!("{} is up to date, skipping", out_path.display()).as_str());
+                mir_path = Some(mir_path_);
+                return Ok(Some(InnerOutput { out: None, fingerprint: None }));
+            }
+        }
+
+        let mut out = mk_output(&mir_path_)?;
+        out.set_target(target_json(tcx))?;
+        out.set_panic_strategy(tcx.sess.panic_strategy().desc())?;
+        out.set_schema(opts.schema())?;
+        let deps_path = mir_path_.with_extension("deps.json");
+        mir_path = Some(mir_path_);
 
         let mut used = Used::default();
         let mut tys = TyIntern::default();
         let mut allocs = AllocIntern::default();
+        let mut missing = MissingReport::default();
+        let mut diags = Diagnostics::default();
         let state = CompileState {
             session: sess,
             tcx,
         };
+        let test_fns = if opts.rust_tests { rust_test_fns(tcx) } else { HashSet::new() };
         let mut ms = MirState {
             mir: None,
             used: &mut used,
             state: &state,
             tys: &mut tys,
             match_span_map: &get_match_spans(),
+            test_fns: &test_fns,
             allocs: &mut allocs,
             export_style: export_style,
+            opts,
+            missing: &mut missing,
+            diags: &mut diags,
         };
 
         // Traits and top-level statics can be enumerated directly.
-        emit_statics(&mut ms, &mut out)?;
+        emit_statics(&mut ms)?;
+        emit_trait_aliases(&mut ms, &mut out)?;
 
         // Everything else is demand-driven, to handle monomorphization.  We start with all
         // #[test] functions, then keep looping until there are no more nodes to process.
         init_instances(&mut ms, &mut out)?;
+        if let Some(j) = allocator_json(&mut ms) {
+            out.set_allocator(j)?;
+        }
 
         while ms.used.has_new() {
             for inst in ms.used.instances.take_new() {
@@ -1135,13 +2493,31 @@ fn analyze_inner<'tcx, O: JsonOutput, F: FnOnce(&Path) -> io::Result<O>>(
             for ti in ms.used.traits.take_new() {
                 emit_trait(&mut ms, &mut out, ti)?;
             }
+            for (inst, idx) in ms.used.promoteds.take_new() {
+                emit_promoted(&mut ms, &mut out, inst, idx)?;
+            }
+            for def_id in ms.used.statics.take_new() {
+                if def_id.is_local() {
+                    emit_static(&mut ms, &mut out, def_id)?;
+                }
+            }
         }
 
         // Any referenced types should normally be emitted immediately after the entry that
         // references them, but we check again here just in case.
         emit_new_defs(&mut ms, &mut out)?;
 
-        Ok(Some(out))
+        if opts.list_missing {
+            report_missing(sess, ms.missing);
+        }
+        if opts.emit_deps {
+            write_deps(tcx, ms.used, &deps_path)?;
+        }
+        if let Some(ref path) = opts.diagnostics {
+            serde_json::to_writer_pretty(File::create(path)?, ms.diags.entries())?;
+        }
+
+        Ok(Some(InnerOutput { out: Some(out), fingerprint }))
     })?;
 
     let mir_path = match mir_path {
@@ -1158,12 +2534,18 @@ pub fn analyze_nonstreaming<'tcx>(
     sess: &Session,
     queries: &'tcx Queries<'tcx>,
     export_style: ExportStyle,
+    opts: &Options,
 ) -> Result<Option<AnalysisData<()>>, serde_cbor::Error> {
-    let opt_ad = analyze_inner(sess, queries, export_style, |_| { Ok(lib_util::Output::default()) })?;
-    let AnalysisData { mir_path, extern_mir_paths, output: out } = match opt_ad {
+    let opt_ad = analyze_inner(sess, queries, export_style, opts,
+                               |_| { Ok(lib_util::Output::default()) })?;
+    let AnalysisData { mir_path, extern_mir_paths, output } = match opt_ad {
         Some(x) => x,
         None => return Ok(None),
     };
+    let out = match output.out {
+        Some(ref out) => out,
+        None => return Ok(Some(AnalysisData { mir_path, extern_mir_paths, output: () })),
+    };
 
     let total_items = out.fns.len() + out.adts.len() + out.statics.len() + out.vtables.len() +
         out.traits.len() + out.intrinsics.len();
@@ -1175,12 +2557,22 @@ pub fn analyze_nonstreaming<'tcx>(
         "traits": out.traits,
         "intrinsics": out.intrinsics,
         "tys": out.tys,
+        "def_id_names": out.def_id_names,
+        "trait_aliases": out.trait_aliases,
+        "target": out.target,
+        "allocator": out.allocator,
+        "panic_strategy": out.panic_strategy,
+        "schema": out.schema,
         "roots": out.roots,
     });
     sess.note_without_error(
         &format!("Indexing MIR ({} items)...", total_items));
     let file = File::create(&mir_path)?;
     lib_util::write_indexed_crate(file, &j)?;
+    // Record the fingerprint only once the `.mir` file is complete.
+    if let Some(ref fp) = output.fingerprint {
+        fp.write()?;
+    }
 
     Ok(Some(AnalysisData { mir_path, extern_mir_paths, output: () }))
 }
@@ -1189,18 +2581,74 @@ pub fn analyze_streaming<'tcx>(
     sess: &Session,
     queries: &'tcx Queries<'tcx>,
     export_style: ExportStyle,
+    opts: &Options,
 ) -> Result<Option<AnalysisData<()>>, serde_cbor::Error> {
-    let opt_ad = analyze_inner(sess, queries, export_style, lib_util::start_streaming)?;
+    let opt_ad = analyze_inner(sess, queries, export_style, opts, lib_util::start_streaming)?;
     let AnalysisData { mir_path, extern_mir_paths, output } = match opt_ad {
         Some(x) => x,
         None => return Ok(None),
     };
-    lib_util::finish_streaming(output)?;
+    if let Some(out) = output.out {
+        lib_util::finish_streaming(out)?;
+        if let Some(ref fp) = output.fingerprint {
+            fp.write()?;
+        }
+    }
     Ok(Some(AnalysisData { mir_path, extern_mir_paths, output: () }))
 }
 
-pub use self::analyze_streaming as analyze;
-pub use analyz::to_json::ExportStyle;
+/// Like `analyze_streaming`, but writes the output in JSON Lines format (see
+/// `lib_util::JsonLinesOutput`) to a `.jsonl` file instead of producing an indexed `.mir` file.
+pub fn analyze_json_lines<'tcx>(
+    sess: &Session,
+    queries: &'tcx Queries<'tcx>,
+    export_style: ExportStyle,
+    opts: &Options,
+) -> Result<Option<AnalysisData<()>>, serde_cbor::Error> {
+    let opt_ad = analyze_inner(sess, queries, export_style, opts,
+                               |path| lib_util::start_json_lines(&path.with_extension("jsonl")))?;
+    let AnalysisData { mir_path, extern_mir_paths, output } = match opt_ad {
+        Some(x) => x,
+        None => return Ok(None),
+    };
+    if let Some(out) = output.out {
+        lib_util::finish_json_lines(out)?;
+        if let Some(ref fp) = output.fingerprint {
+            fp.write()?;
+        }
+    }
+    let mir_path = mir_path.with_extension("jsonl");
+    Ok(Some(AnalysisData { mir_path, extern_mir_paths, output: () }))
+}
+
+/// Translate the crate without writing any output, for `--list-missing`.  This always returns
+/// `Ok(None)`, since there is no `.mir` file to report.
+pub fn analyze_dry_run<'tcx>(
+    sess: &Session,
+    queries: &'tcx Queries<'tcx>,
+    export_style: ExportStyle,
+    opts: &Options,
+) -> Result<Option<AnalysisData<()>>, serde_cbor::Error> {
+    analyze_inner(sess, queries, export_style, opts, |_| Ok(lib_util::NullOutput))?;
+    Ok(None)
+}
+
+/// Analyze the crate, using the output format selected by `opts`.
+pub fn analyze<'tcx>(
+    sess: &Session,
+    queries: &'tcx Queries<'tcx>,
+    export_style: ExportStyle,
+    opts: &Options,
+) -> Result<Option<AnalysisData<()>>, serde_cbor::Error> {
+    if opts.list_missing {
+        analyze_dry_run(sess, queries, export_style, opts)
+    } else if opts.json_lines {
+        analyze_json_lines(sess, queries, export_style, opts)
+    } else {
+        analyze_streaming(sess, queries, export_style, opts)
+    }
+}
+pub use analyz::to_json::{ExportStyle, Options, SCHEMA_VERSION};
 
 fn make_attr(key: &str, value: &str) -> ast::Attribute {
     ast::Attribute {
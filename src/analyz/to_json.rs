@@ -1,15 +1,18 @@
 use rustc_hir::def_id::{DefId, LOCAL_CRATE};
-use rustc_middle::mir::{Body, interpret};
+use rustc_middle::mir::{self, Body, interpret};
 use rustc_middle::ty::{self, TyCtxt, DynKind};
 use rustc_session::Session;
 use rustc_span::Span;
 use rustc_span::symbol::Symbol;
 use serde_json;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::collections::{HashMap, HashSet, hash_map};
 use std::hash::{Hash, Hasher};
 use std::ops::Deref;
 use std::mem;
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicU32, Ordering};
 
 pub struct CompileState<'a, 'tcx> {
     pub session: &'a Session,
@@ -58,18 +61,37 @@ impl<T: Hash+Eq> Deref for UsedSet<T> {
 #[derive(Default)]
 pub struct Used<'tcx> {
     pub types: UsedSet<AdtInst<'tcx>>,
-    pub vtables: UsedSet<ty::PolyTraitRef<'tcx>>,
+    /// Vtables, identified by the `PolyTraitRef` for the concrete type and the auto traits of the
+    /// `dyn` type it's cast to.  The auto traits don't change the vtable's contents, but they're
+    /// part of its `trait_id`, which must match the `Dynamic` type.
+    pub vtables: UsedSet<(ty::PolyTraitRef<'tcx>, Vec<DefId>)>,
     pub instances: UsedSet<ty::Instance<'tcx>>,
     pub traits: UsedSet<TraitInst<'tcx>>,
+    /// `DefId`s that appear in the output, for the `--def-id-names` table.
+    pub def_ids: UsedSet<DefId>,
+    /// Promoted bodies, identified by the instance they belong to and their index.  These are
+    /// tracked separately from `instances`, since a constant can refer to a promoted of an item
+    /// whose own body is never translated in this crate.  Promoteds of instances that an upstream
+    /// crate translated are emitted by that crate, along with the body.
+    pub promoteds: UsedSet<(ty::Instance<'tcx>, mir::Promoted)>,
+    /// Statics, including thread-locals.  Only statics defined in the current crate are emitted;
+    /// the rest are emitted by the crates that define them.
+    pub statics: UsedSet<DefId>,
 }
 
 impl<'tcx> Used<'tcx> {
     pub fn has_new(&self) -> bool {
-        let Used { ref types, ref vtables, ref instances, ref traits } = *self;
+        // `def_ids` never introduces new items to translate.  It's drained by `emit_new_defs`.
+        let Used {
+            ref types, ref vtables, ref instances, ref traits, def_ids: _, ref promoteds,
+            ref statics,
+        } = *self;
         types.has_new() ||
         vtables.has_new() ||
         instances.has_new() ||
-        traits.has_new()
+        traits.has_new() ||
+        promoteds.has_new() ||
+        statics.has_new()
     }
 }
 
@@ -80,13 +102,22 @@ impl<'tcx> Used<'tcx> {
 /// types `dyn Iterator<Item = u8>` and `dyn Iterator<Item = i32>` are based on the same `TraitRef`
 /// (and the same trait `DefId`), but have distinct `TraitInst`s and distinct vtable signatures,
 /// because their `next` methods return different types.
+///
+/// Auto traits don't affect the vtable signature, but we still include them, so that `dyn Foo`
+/// and `dyn Foo + Send` get distinct `TraitInst`s (and distinct `Dynamic` types in the output).
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct TraitInst<'tcx> {
     pub trait_ref: Option<ty::ExistentialTraitRef<'tcx>>,
     pub projs: Vec<ty::ExistentialProjection<'tcx>>,
+    /// Auto traits, in the order rustc uses for existential predicates.
+    pub auto_traits: Vec<DefId>,
 }
 
 impl<'tcx> TraitInst<'tcx> {
+    /// Obtain the `TraitInst` of a `dyn` type from its predicates.  The auto traits are kept, so
+    /// `dyn Trait`, `dyn Trait + Send`, and `dyn Trait + Send + Sync` all produce distinct
+    /// `TraitInst`s.  rustc interns the predicates in a canonical order, so `dyn Trait + Sync +
+    /// Send` is the same type as `dyn Trait + Send + Sync` and produces the same `TraitInst`.
     pub fn from_dynamic_predicates(
         tcx: TyCtxt<'tcx>,
         preds: &'tcx ty::List<ty::Binder<'tcx, ty::ExistentialPredicate<'tcx>>>,
@@ -96,7 +127,12 @@ impl<'tcx> TraitInst<'tcx> {
             .map(|proj| tcx.erase_late_bound_regions(proj))
             .collect::<Vec<_>>();
         projs.sort_by_key(|p| p.def_id);
-        TraitInst { trait_ref, projs }
+        let auto_traits = if target_schema() >= 2 {
+            preds.auto_traits().collect()
+        } else {
+            Vec::new()
+        };
+        TraitInst { trait_ref, projs, auto_traits }
     }
 
     /// Obtain the `TraitInst` from a concrete `TraitRef`.  This erases the `Self` type, and
@@ -146,19 +182,32 @@ impl<'tcx> TraitInst<'tcx> {
         }
         projs.sort_by_key(|p| p.def_id);
 
+        // When the `Self` type is itself a trait object (as in `InstanceDef::Virtual`), keep its
+        // auto traits, so the result matches the `TraitInst` of the `Dynamic` type.
+        let auto_traits = match *trait_ref.self_ty().kind() {
+            ty::TyKind::Dynamic(preds, _, _) if target_schema() >= 2 =>
+                preds.auto_traits().collect(),
+            _ => Vec::new(),
+        };
+
         TraitInst {
             trait_ref: Some(ex_trait_ref),
             projs,
+            auto_traits,
         }
     }
 
     pub fn dyn_ty(&self, tcx: TyCtxt<'tcx>) -> Option<ty::Ty<'tcx>> {
         let trait_ref = self.trait_ref?;
-        let mut preds = Vec::with_capacity(self.projs.len() + 1);
+        let mut preds = Vec::with_capacity(self.projs.len() + self.auto_traits.len() + 1);
         preds.push(ty::Binder::dummy(ty::ExistentialPredicate::Trait(trait_ref)));
         preds.extend(
             self.projs.iter().map(|p| ty::Binder::dummy(ty::ExistentialPredicate::Projection(*p))),
         );
+        preds.extend(
+            self.auto_traits.iter()
+                .map(|&did| ty::Binder::dummy(ty::ExistentialPredicate::AutoTrait(did))),
+        );
         let preds = tcx.intern_poly_existential_predicates(&preds);
         // Always emit `DynKind::Dyn`.  We don't support `dyn*` (`DynKind::DynStar`) yet.
         Some(tcx.mk_dynamic(preds, tcx.mk_region(ty::RegionKind::ReErased), DynKind::Dyn))
@@ -196,6 +245,9 @@ impl<'tcx> AdtInst<'tcx> {
 #[derive(Default, Debug)]
 pub struct TyIntern<'tcx> {
     map: HashMap<ty::Ty<'tcx>, String>,
+    /// The position of each type in the order it was interned.  This is also its position in
+    /// the crate's type table, which `--flat-types` uses to refer to it.
+    index: HashMap<ty::Ty<'tcx>, usize>,
     /// Types that are newly referenced since the last `take_new_types()`.
     new_vals: Vec<serde_json::Value>,
 }
@@ -261,9 +313,15 @@ impl<'tcx> TyIntern<'tcx> {
         }));
         let old = self.map.insert(ty, id.clone());
         assert!(old.is_none(), "duplicate insert for type {:?}", ty);
+        let idx = self.index.len();
+        self.index.insert(ty, idx);
         id
     }
 
+    pub fn index(&self, ty: ty::Ty<'tcx>) -> Option<usize> {
+        self.index.get(&ty).cloned()
+    }
+
     pub fn take_new_types(&mut self) -> Vec<serde_json::Value> {
         mem::replace(&mut self.new_vals, Vec::new())
     }
@@ -284,6 +342,234 @@ impl Default for ExportStyle {
     }
 }
 
+/// The version of the JSON output format.  This should be incremented whenever a change to the
+/// output would break existing consumers, such as changing the shape of an existing field.
+/// Adding new fields doesn't require a new version.
+///
+/// Version 2 made these changes, which `--target-schema=1` undoes:
+///
+/// * The `size` of an `Array` type is `{"val": N}` or a const parameter, rather than a `Const`.
+/// * `TraitInst`s include the auto traits of the `dyn` type, so `dyn Foo` and `dyn Foo + Send`
+///   have distinct `trait_id`s.
+/// * Unwind edges (`unwind` and `cleanup`) are `null` under `-C panic=abort`.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// The schema version being emitted.  This is a global, like `ty_json::set_stable_names`, since
+/// `TraitInst`s are built in places that have only a `TyCtxt`.
+static TARGET_SCHEMA: AtomicU32 = AtomicU32::new(SCHEMA_VERSION);
+
+pub fn set_target_schema(version: u32) {
+    TARGET_SCHEMA.store(version, Ordering::Relaxed);
+}
+
+pub fn target_schema() -> u32 {
+    TARGET_SCHEMA.load(Ordering::Relaxed)
+}
+
+/// Settings controlled by mir-json-specific command-line flags.  These flags are not understood
+/// by `rustc`, so they must be removed from the argument list (see `Options::take_from_args`)
+/// before it is passed on to `rustc_driver`.
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    /// Crates whose functions are emitted as declarations only (`--opaque-crate=a,b`).  This is
+    /// useful when the consumer supplies its own models for these crates.
+    pub opaque_crates: HashSet<String>,
+    /// Report every item that uses a construct mir-json can only partially translate, such as a
+    /// stubbed-out type or predicate (`--list-missing`).  This is a dry run: no output is
+    /// written.
+    pub list_missing: bool,
+    /// Emit unevaluated constants symbolically, as a reference to their initializer, instead of
+    /// const-evaluating them (`--lazy-consts`).
+    pub lazy_consts: bool,
+    /// Emit 128-bit integer constants as `{"hi": ..., "lo": ...}` pairs of 64-bit words instead
+    /// of decimal strings (`--split-wide-ints`).
+    pub split_wide_ints: bool,
+    /// Annotate each type with whether it is `Copy`, `Sized`, and `Freeze`
+    /// (`--marker-traits`).
+    pub marker_traits: bool,
+    /// Annotate each type with whether it needs to be dropped (`--needs-drop`).
+    pub needs_drop: bool,
+    /// Hash substs in mangled names with mir-json's own scheme instead of rustc's
+    /// `StableHasher`, so names don't change between rustc versions (`--stable-names`).
+    pub stable_names: bool,
+    /// Emit the variance of each generic parameter of ADTs and functions (`--variances`).
+    pub variances: bool,
+    /// Emit the source scope tree of each body, and the scope of each statement and terminator
+    /// (`--source-scopes`).
+    pub source_scopes: bool,
+    /// Describe the late-bound regions of each function signature, which are otherwise erased
+    /// (`--late-bound-regions`).
+    pub late_bound_regions: bool,
+    /// Translate MIR built with no optimizations, so that no function is inlined into another
+    /// (`--unoptimized-mir`).  This is done by passing `-Z mir-opt-level=0` to rustc.
+    pub unoptimized_mir: bool,
+    /// Annotate raw pointer derefs and packed-struct field projections with the alignment the
+    /// access requires (`--place-align`).
+    pub place_align: bool,
+    /// Emit declarations for the foreign functions the crate calls, from `extern` blocks
+    /// (`--extern-fns`).  These have no `body` or `args`.
+    pub extern_fns: bool,
+    /// Annotate each type with its rustc-printed form, for debugging (`--pretty-types`).
+    pub pretty_types: bool,
+    /// Refer to types by their index in the type table instead of by their string ID
+    /// (`--flat-types`).
+    pub flat_types: bool,
+    /// Split critical edges in each MIR body before emitting it (`--split-critical-edges`).
+    pub split_critical_edges: bool,
+    /// Emit the instantiated `where` clauses of each function (`--instance-predicates`).
+    pub instance_predicates: bool,
+    /// Emit a table mapping each `def_id_str` name to its human-readable path
+    /// (`--def-id-names`).
+    pub def_id_names: bool,
+    /// Write unindexed JSON Lines output to a `.jsonl` file instead of the usual indexed `.mir`
+    /// file (`--json-lines`).
+    pub json_lines: bool,
+    /// Write the sorted names of all instances, ADTs, and traits that were translated to a
+    /// `.deps.json` file next to the output (`--emit-deps`).
+    pub emit_deps: bool,
+    /// Emit only declarations for the crate's public functions and statics, skipping all bodies
+    /// and constant evaluation (`--headers-only`).
+    pub headers_only: bool,
+    /// Also treat the crate's `#[test]` functions as roots, and mark them in the output
+    /// (`--rust-tests`).  These only exist when rustc is invoked with `--test`.
+    pub rust_tests: bool,
+    /// Directory to write a Graphviz file of each translated function's control-flow graph to,
+    /// for debugging (`--dump-mir-cfg=DIR`).
+    pub dump_mir_cfg: Option<PathBuf>,
+    /// The schema version to emit, for consumers that predate `SCHEMA_VERSION`
+    /// (`--target-schema=N`).  `None` means the current version.  When the format changes, the
+    /// serialization of each changed field should check `schema()` and emit the older shape.
+    pub target_schema: Option<u32>,
+    /// File to write structured diagnostics to, as a JSON array (`--diagnostics=FILE`).
+    pub diagnostics: Option<PathBuf>,
+    /// Translate only the functions and statics whose path contains this string, plus anything
+    /// they use (`--filter=PATTERN`).
+    pub filter: Option<String>,
+    /// Directory for crate fingerprints (`--incremental DIR`).  A crate whose fingerprint is
+    /// unchanged since the last run is not translated again.
+    pub incremental: Option<PathBuf>,
+    /// All mir-json flags, exactly as given.  These are part of the `--incremental` fingerprint,
+    /// since they affect the output.
+    pub flags: Vec<String>,
+}
+
+impl Options {
+    /// Remove all mir-json flags from `args`, and return the `Options` they describe.
+    pub fn take_from_args(args: &mut Vec<String>) -> Options {
+        let mut opts = Options::default();
+        let mut rest = Vec::with_capacity(args.len());
+        let mut it = mem::replace(args, Vec::new()).into_iter();
+        while let Some(arg) = it.next() {
+            if let Some(names) = arg.strip_prefix("--opaque-crate=") {
+                opts.opaque_crates.extend(names.split(',').map(|s| s.to_owned()));
+            } else if let Some(pat) = arg.strip_prefix("--filter=") {
+                opts.filter = Some(pat.to_owned());
+            } else if let Some(path) = arg.strip_prefix("--diagnostics=") {
+                opts.diagnostics = Some(PathBuf::from(path));
+            } else if let Some(n) = arg.strip_prefix("--target-schema=") {
+                let n = n.parse::<u32>().unwrap_or_else(|e| {
+                    eprintln!("error: bad schema version {:?} in --target-schema: {}", n, e);
+                    process::exit(1);
+                });
+                if let Err(msg) = check_target_schema(n) {
+                    eprintln!("error: {}", msg);
+                    process::exit(1);
+                }
+                opts.target_schema = Some(n);
+            } else if let Some(dir) = arg.strip_prefix("--dump-mir-cfg=") {
+                opts.dump_mir_cfg = Some(PathBuf::from(dir));
+            } else if arg == "--list-missing" {
+                opts.list_missing = true;
+            } else if arg == "--lazy-consts" {
+                opts.lazy_consts = true;
+            } else if arg == "--split-wide-ints" {
+                opts.split_wide_ints = true;
+            } else if arg == "--split-critical-edges" {
+                opts.split_critical_edges = true;
+            } else if arg == "--instance-predicates" {
+                opts.instance_predicates = true;
+            } else if arg == "--json-lines" {
+                opts.json_lines = true;
+            } else if arg == "--def-id-names" {
+                opts.def_id_names = true;
+            } else if arg == "--headers-only" {
+                opts.headers_only = true;
+            } else if arg == "--rust-tests" {
+                opts.rust_tests = true;
+            } else if arg == "--emit-deps" {
+                opts.emit_deps = true;
+            } else if arg == "--flat-types" {
+                opts.flat_types = true;
+            } else if arg == "--pretty-types" {
+                opts.pretty_types = true;
+            } else if arg == "--marker-traits" {
+                opts.marker_traits = true;
+            } else if arg == "--needs-drop" {
+                opts.needs_drop = true;
+            } else if arg == "--stable-names" {
+                opts.stable_names = true;
+            } else if arg == "--variances" {
+                opts.variances = true;
+            } else if arg == "--source-scopes" {
+                opts.source_scopes = true;
+            } else if arg == "--late-bound-regions" {
+                opts.late_bound_regions = true;
+            } else if arg == "--unoptimized-mir" {
+                opts.unoptimized_mir = true;
+            } else if arg == "--place-align" {
+                opts.place_align = true;
+            } else if arg == "--extern-fns" {
+                opts.extern_fns = true;
+            } else if arg == "--incremental" {
+                let dir = it.next().unwrap_or_else(|| {
+                    eprintln!("error: --incremental requires a directory argument");
+                    process::exit(1);
+                });
+                opts.incremental = Some(PathBuf::from(&dir));
+                opts.flags.push(arg);
+                opts.flags.push(dir);
+                continue;
+            } else {
+                rest.push(arg);
+                continue;
+            }
+            opts.flags.push(arg);
+        }
+        if opts.flat_types && !opts.json_lines {
+            // Type indices are only meaningful within one crate, so they can't survive linking,
+            // which also finds dependencies by scanning for string IDs.
+            eprintln!("error: --flat-types requires --json-lines");
+            process::exit(1);
+        }
+        if opts.unoptimized_mir {
+            // `optimized_mir` still runs the passes needed for correctness, such as drop
+            // elaboration, but skips inlining and other optimizations.  This goes last so that
+            // it overrides any `mir-opt-level` given explicitly.
+            rest.push("-Zmir-opt-level=0".to_owned());
+        }
+        *args = rest;
+        opts
+    }
+
+    /// The schema version to emit: the one given with `--target-schema`, or else the current one.
+    pub fn schema(&self) -> u32 {
+        self.target_schema.unwrap_or(SCHEMA_VERSION)
+    }
+}
+
+/// Check that we can emit output in schema version `n`.  Older versions can be supported as long
+/// as the changes since then can be undone faithfully; anything else is an error, since the
+/// consumer would misread the output.  Every version so far is supported.
+fn check_target_schema(n: u32) -> Result<(), String> {
+    if n == 0 || n > SCHEMA_VERSION {
+        return Err(format!(
+            "--target-schema={} is not supported: this mir-json emits schema versions 1 to {}",
+            n, SCHEMA_VERSION,
+        ));
+    }
+    Ok(())
+}
+
 #[derive(Default, Debug)]
 pub struct AllocIntern<'tcx> {
     /// We key this map on both ConstAllocations and their Tys. Keying the map
@@ -320,6 +606,43 @@ impl<'tcx> AllocIntern<'tcx> {
     }
 }
 
+/// Constructs that hit a stub or fallback arm during translation, grouped by the item that was
+/// being emitted at the time.  Only populated when `Options::list_missing` is set.
+#[derive(Default, Debug)]
+pub struct MissingReport {
+    cur_item: String,
+    entries: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl MissingReport {
+    /// Set the name of the item currently being emitted.  Subsequent `record` calls are
+    /// attributed to this item.
+    pub fn set_item(&mut self, name: &str) {
+        self.cur_item = name.to_owned();
+    }
+
+    pub fn record(&mut self, what: &str) {
+        self.entries.entry(self.cur_item.clone()).or_insert_with(BTreeSet::new)
+            .insert(what.to_owned());
+    }
+
+    pub fn entries(&self) -> &BTreeMap<String, BTreeSet<String>> {
+        &self.entries
+    }
+}
+
+/// Problems found during translation, recorded for `--diagnostics=FILE`.
+#[derive(Default)]
+pub struct Diagnostics {
+    entries: Vec<serde_json::Value>,
+}
+
+impl Diagnostics {
+    pub fn entries(&self) -> &[serde_json::Value] {
+        &self.entries
+    }
+}
+
 pub struct MirState<'a, 'tcx : 'a> {
     pub mir: Option<&'tcx Body<'tcx>>,
     pub used: &'a mut Used<'tcx>,
@@ -336,8 +659,37 @@ pub struct MirState<'a, 'tcx : 'a> {
     /// rewritten.  This seems okay for now since the user is mostly interested in coverage in
     /// their own top-level crate anyway.
     pub match_span_map: &'a HashMap<Span, Span>,
+    /// The `#[test]` functions of the local crate.  This is only populated under `--rust-tests`.
+    pub test_fns: &'a HashSet<DefId>,
     pub allocs: &'a mut AllocIntern<'tcx>,
     pub export_style: ExportStyle,
+    pub opts: &'a Options,
+    pub missing: &'a mut MissingReport,
+    pub diags: &'a mut Diagnostics,
+}
+
+impl<'a, 'tcx> MirState<'a, 'tcx> {
+    /// Note that the current item uses a construct that we can't fully translate yet.
+    pub fn note_missing(&mut self, what: &str) {
+        if self.opts.list_missing {
+            self.missing.record(what);
+        }
+    }
+
+    /// Report a problem with the translation.  The message is always printed to stderr.  With
+    /// `--diagnostics`, it's also recorded along with `kind`, which identifies the type of
+    /// problem, and `item`, which names the definition involved.
+    pub fn report(&mut self, level: &str, kind: &str, item: Option<String>, msg: String) {
+        eprintln!("{}: {}", level, msg);
+        if self.opts.diagnostics.is_some() {
+            self.diags.entries.push(json!({
+                "level": level,
+                "kind": kind,
+                "item": item,
+                "message": msg,
+            }));
+        }
+    }
 }
 
 /// Trait for converting MIR elements to JSON.
@@ -8,14 +8,19 @@ use rustc_const_eval::interpret::{self, InterpCx, InterpResult, MPlaceTy, Proven
 use rustc_const_eval::const_eval::CheckAlignment;
 use rustc_middle::bug;
 use rustc_middle::ty;
-use rustc_middle::ty::{AdtKind, DynKind, TyCtxt, TypeFoldable, TypeVisitable};
+use rustc_middle::ty::{AdtKind, DynKind, TyCtxt, TypeFoldable, TypeVisitable, TypeVisitor};
+use rustc_middle::ty::layout::PrimitiveExt;
 use rustc_middle::ty::util::{IntTypeExt};
 use rustc_query_system::ich::StableHashingContext;
 use rustc_target::spec::abi;
-use rustc_target::abi::{Align, FieldsShape, HasDataLayout, Size};
+use rustc_target::abi::{
+    Align, FieldsShape, HasDataLayout, Size, TagEncoding, TyAndLayout, VariantIdx, Variants,
+};
 use rustc_span::DUMMY_SP;
 use serde_json;
 use std::fmt::Write as FmtWrite;
+use std::ops::{Bound, ControlFlow};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::usize;
 
 use analyz::to_json::*;
@@ -38,9 +43,28 @@ basic_json_enum_impl!(ty::IntTy);
 basic_json_enum_impl!(ty::UintTy);
 basic_json_enum_impl!(hir::Mutability);
 basic_json_enum_impl!(hir::def::CtorKind);
-basic_json_enum_impl!(mir::CastKind);
+basic_json_enum_impl!(hir::Movability);
 basic_json_enum_impl!(abi::Abi);
 
+impl ToJson<'_> for mir::CastKind {
+    fn to_json(&self, _: &mut MirState) -> serde_json::Value {
+        // The `kind` string matches the output of `basic_json_enum_impl!`, for compatibility.
+        // Pointer coercions additionally get their `PointerCast` as a separate field, so
+        // consumers don't need to parse it out of the `kind`.
+        let mut j = json!({"kind": format!("{:?}", self)});
+        if let mir::CastKind::Pointer(pc) = *self {
+            j["pointer_cast"] = match pc {
+                ty::adjustment::PointerCast::ClosureFnPointer(unsafety) => json!({
+                    "kind": "ClosureFnPointer",
+                    "unsafety": format!("{:?}", unsafety),
+                }),
+                _ => json!({"kind": format!("{:?}", pc)}),
+            };
+        }
+        j
+    }
+}
+
 impl ToJson<'_> for mir::BorrowKind {
     fn to_json(&self, _mir: &mut MirState) -> serde_json::Value {
         match self {
@@ -71,34 +95,198 @@ impl ToJson<'_> for ty::VariantDiscr {
 pub fn def_id_str(tcx: TyCtxt, def_id: hir::def_id::DefId) -> String {
     // Based on rustc/ty/context.rs.html TyCtxt::def_path_debug_str
     let crate_name = tcx.crate_name(def_id.krate);
-    let disambig = tcx.crate_hash(def_id.krate);
     let defpath = tcx.def_path(def_id);
     format!(
         "{}/{}{}",
         crate_name,
-        &disambig.to_string()[..8],
+        crate_disambiguator(tcx, def_id.krate),
         defpath.to_string_no_crate_verbose(),
     )
 }
 
+/// Get the string that distinguishes `krate` from other crates with the same name, as used in
+/// `def_id_str`.
+fn crate_disambiguator(tcx: TyCtxt, krate: hir::def_id::CrateNum) -> String {
+    tcx.crate_hash(krate).to_string()[..8].to_owned()
+}
+
+/// Describe the crate that defines `def_id`, using the same name and disambiguator that appear
+/// in `def_id_str`.  Consumers can use this to group items by crate without parsing names.
+pub fn crate_json(tcx: TyCtxt, def_id: hir::def_id::DefId) -> serde_json::Value {
+    json!({
+        "name": tcx.crate_name(def_id.krate).as_str(),
+        "disambiguator": crate_disambiguator(tcx, def_id.krate),
+    })
+}
+
+/// List the variance of each generic parameter of `def_id`, including those inherited from its
+/// parent, in the same order as its substs.  Returns `null` for items rustc doesn't compute
+/// variances for.
+pub fn variances_json(tcx: TyCtxt, def_id: hir::def_id::DefId) -> serde_json::Value {
+    use rustc_hir::def::DefKind;
+    match tcx.def_kind(def_id) {
+        DefKind::Struct | DefKind::Enum | DefKind::Union | DefKind::Fn | DefKind::AssocFn => {},
+        _ => return serde_json::Value::Null,
+    }
+    tcx.variances_of(def_id).iter()
+        .map(|v| match v {
+            ty::Variance::Covariant => "Covariant",
+            ty::Variance::Invariant => "Invariant",
+            ty::Variance::Contravariant => "Contravariant",
+            ty::Variance::Bivariant => "Bivariant",
+        })
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// Whether `ext_def_id_str` hashes with `stable_names_hash` instead of rustc's `StableHasher`
+/// (`--stable-names`).  Names are built in many places that only have a `TyCtxt` at hand, so this
+/// is set once in `analyze_inner` instead of being passed around with the other `Options`.
+static STABLE_NAMES: AtomicBool = AtomicBool::new(false);
+
+pub fn set_stable_names(enable: bool) {
+    STABLE_NAMES.store(enable, Ordering::Relaxed);
+}
+
 pub fn ext_def_id_str<'tcx, T>(
     tcx: TyCtxt<'tcx>,
     def_id: hir::def_id::DefId,
     prefix: &str,
     extra: T,
 ) -> String
-where T: for<'a> HashStable<StableHashingContext<'a>> {
+where T: for<'a> HashStable<StableHashingContext<'a>> + TypeVisitable<'tcx> {
     let base = def_id_str(tcx, def_id);
 
-    // Based on librustc_codegen_utils/symbol_names/legacy.rs get_symbol_hash
-    let hash: u64 = tcx.with_stable_hashing_context(|mut hcx| {
-        let mut hasher = StableHasher::new();
-        extra.hash_stable(&mut hcx, &mut hasher);
-        hasher.finish()
-    });
+    let hash: u64 = if STABLE_NAMES.load(Ordering::Relaxed) {
+        stable_names_hash(tcx, extra)
+    } else {
+        // Based on librustc_codegen_utils/symbol_names/legacy.rs get_symbol_hash
+        tcx.with_stable_hashing_context(|mut hcx| {
+            let mut hasher = StableHasher::new();
+            extra.hash_stable(&mut hcx, &mut hasher);
+            hasher.finish()
+        })
+    };
     format!("{}::{}{:016x}[0]", base, prefix, hash)
 }
 
+/// Hash `x` in a way that doesn't depend on rustc's `StableHasher`, whose output may change
+/// between rustc versions.  We write out a tag for each type, const, and region in `x` (in the
+/// order `TypeVisitor` visits them) and hash the result with 64-bit FNV-1a.  Each tag determines
+/// how many children follow it, so distinct values always produce distinct strings.
+fn stable_names_hash<'tcx, T: TypeVisitable<'tcx>>(tcx: TyCtxt<'tcx>, x: T) -> u64 {
+    let mut key = StableNameKey { tcx, buf: String::new() };
+    let _ = x.visit_with(&mut key);
+    fnv1a_hash(key.buf.as_bytes())
+}
+
+/// Hash `bytes` with 64-bit FNV-1a.  Unlike the hashers in `std`, the algorithm is fixed, so the
+/// result is the same for every build of mir-json.
+pub fn fnv1a_hash(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for &b in bytes {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+struct StableNameKey<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    buf: String,
+}
+
+impl<'tcx> TypeVisitor<'tcx> for StableNameKey<'tcx> {
+    fn visit_ty(&mut self, ty: ty::Ty<'tcx>) -> ControlFlow<!> {
+        use rustc_middle::ty::TyKind::*;
+        let tcx = self.tcx;
+        let tag = match *ty.kind() {
+            Bool => "bool".to_owned(),
+            Char => "char".to_owned(),
+            Int(i) => i.name_str().to_owned(),
+            Uint(u) => u.name_str().to_owned(),
+            Float(f) => f.name_str().to_owned(),
+            Str => "str".to_owned(),
+            Never => "!".to_owned(),
+            Array(..) => "array".to_owned(),
+            Slice(_) => "slice".to_owned(),
+            RawPtr(tm) => format!("ptr_{:?}", tm.mutbl),
+            Ref(_, _, mutbl) => format!("ref_{:?}", mutbl),
+            Tuple(tys) => format!("tuple{}", tys.len()),
+            FnPtr(sig) => format!("fnptr{}_{:?}_{:?}_{}",
+                sig.inputs().skip_binder().len(), sig.unsafety(), sig.abi(), sig.c_variadic()),
+            // The visitor doesn't visit `DefId`s, so we include the traits and associated types
+            // named in the predicates here.  Otherwise `dyn Debug` and `dyn Display` would be
+            // indistinguishable.
+            Dynamic(preds, _, kind) => {
+                let mut s = format!("dyn{}_{:?}", preds.len(), kind);
+                for pred in preds.iter() {
+                    let (pred_kind, did) = match pred.skip_binder() {
+                        ty::ExistentialPredicate::Trait(tr) => ("trait", tr.def_id),
+                        ty::ExistentialPredicate::Projection(p) => ("proj", p.def_id),
+                        ty::ExistentialPredicate::AutoTrait(did) => ("auto", did),
+                    };
+                    let (hi, lo) = tcx.def_path_hash(did).0.as_value();
+                    write!(s, "_{}_{:016x}{:016x}", pred_kind, hi, lo).unwrap();
+                }
+                s
+            },
+            GeneratorWitness(tys) => format!("witness{}", tys.skip_binder().len()),
+            Adt(adt, _) => format!("adt_{}", def_id_str(tcx, adt.did())),
+            Foreign(did) => format!("foreign_{}", def_id_str(tcx, did)),
+            FnDef(did, _) => format!("fndef_{}", def_id_str(tcx, did)),
+            Closure(did, _) => format!("closure_{}", def_id_str(tcx, did)),
+            Generator(did, _, mov) => format!("generator_{}_{:?}", def_id_str(tcx, did), mov),
+            Alias(kind, ref alias) =>
+                format!("alias_{:?}_{}", kind, def_id_str(tcx, alias.def_id)),
+            // These shouldn't appear in monomorphized names.
+            Param(_) | Bound(..) | Placeholder(_) | Infer(_) | Error(_) => format!("{:?}", ty),
+        };
+        self.buf.push_str(&tag);
+        self.buf.push(' ');
+        ty.super_visit_with(self)
+    }
+
+    fn visit_const(&mut self, c: ty::Const<'tcx>) -> ControlFlow<!> {
+        let tag = match c.kind() {
+            ty::ConstKind::Value(val) => {
+                // The `Debug` output of a valtree may change between rustc versions, so we
+                // write out its structure ourselves.
+                let mut s = "const".to_owned();
+                push_valtree_key(&mut s, val);
+                s
+            },
+            ty::ConstKind::Unevaluated(uv) =>
+                format!("unevaluated_{}", def_id_str(self.tcx, uv.def.did)),
+            kind => format!("const_{:?}", kind),
+        };
+        self.buf.push_str(&tag);
+        self.buf.push(' ');
+        c.super_visit_with(self)
+    }
+
+    fn visit_region(&mut self, _: ty::Region<'tcx>) -> ControlFlow<!> {
+        self.buf.push_str("' ");
+        ControlFlow::Continue(())
+    }
+}
+
+/// Write a description of `val` to `buf` for `StableNameKey`.  Leaves give their size and bits;
+/// branches give their number of children, which follow.
+fn push_valtree_key(buf: &mut String, val: ty::ValTree) {
+    match val {
+        ty::ValTree::Leaf(int) => {
+            write!(buf, "_leaf{}_{:x}", int.size().bytes(), int.assert_bits(int.size())).unwrap();
+        },
+        ty::ValTree::Branch(children) => {
+            write!(buf, "_branch{}", children.len()).unwrap();
+            for &child in children {
+                push_valtree_key(buf, child);
+            }
+        },
+    }
+}
+
 pub fn adt_inst_id_str<'tcx>(
     tcx: TyCtxt<'tcx>,
     ai: AdtInst<'tcx>,
@@ -174,27 +362,54 @@ pub fn get_fn_def_name<'tcx>(
     // Compute the mangled name of the monomorphized instance being called.
     if let Ok(Some(inst)) = inst {
         mir.used.instances.insert(inst);
+        if mir.opts.def_id_names {
+            mir.used.def_ids.insert(inst.def_id());
+        }
         inst_id_str(mir.state.tcx, inst)
     } else {
-        eprintln!(
-            "error: failed to resolve FnDef Instance: {:?}, {:?}",
-            defid, substs,
-        );
-        def_id_str(mir.state.tcx, defid)
+        let name = def_id_str(mir.state.tcx, defid);
+        // Constants are named through here too, such as the initializers of lazy constants and
+        // explicit enum discriminants.
+        let msg = match mir.state.tcx.def_kind(defid) {
+            hir::def::DefKind::Const |
+            hir::def::DefKind::AssocConst |
+            hir::def::DefKind::AnonConst |
+            hir::def::DefKind::InlineConst => format!(
+                "failed to resolve the initializer of constant {:?}, {:?}",
+                defid, substs,
+            ),
+            _ => format!(
+                "failed to resolve FnDef Instance: {:?}, {:?}",
+                defid, substs,
+            ),
+        };
+        mir.report("error", "resolve_failed", Some(name.clone()), msg);
+        name
     }
 }
 
+/// Get the name of promoted `promoted` of the body of `defid`, or the name of `defid` itself if
+/// `promoted` is `None`.  As a side effect, this marks the promoted as "used", so its body will be
+/// emitted too.
 pub fn get_promoted_name<'tcx>(
     mir: &mut MirState<'_, 'tcx>,
     defid: DefId,
     substs: ty::subst::SubstsRef<'tcx>,
     promoted: Option<mir::Promoted>,
 ) -> String {
-    let parent = get_fn_def_name(mir, defid, substs);
     let idx = match promoted {
         Some(x) => x,
-        None => return parent,
+        None => return get_fn_def_name(mir, defid, substs),
     };
+    // The promoted belongs to the body of `defid` itself, so we use the unresolved instance here.
+    // Resolving it could select a different body, such as an impl overriding a trait's default
+    // method.
+    let inst = ty::Instance::new(defid, substs);
+    mir.used.promoteds.insert((inst, idx));
+    promoted_name(&inst_id_str(mir.state.tcx, inst), idx)
+}
+
+pub fn promoted_name(parent: &str, idx: mir::Promoted) -> String {
     format!("{}::{{{{promoted}}}}[{}]", parent, idx.as_usize())
 }
 
@@ -211,8 +426,95 @@ pub fn get_drop_fn_name<'tcx>(
     Some(inst_id_str(mir.state.tcx, inst))
 }
 
+/// Describe what the drop glue for `ty` does: first call the type's `Drop::drop` impl (if any),
+/// then drop each field in declaration order.  Only fields that need dropping are listed, and
+/// each one's drop glue is marked as used.  Enums list their fields separately for each variant.
+fn drop_glue_json<'tcx>(
+    mir: &mut MirState<'_, 'tcx>,
+    ty: ty::Ty<'tcx>,
+) -> serde_json::Value {
+    fn field_drops<'tcx>(
+        mir: &mut MirState<'_, 'tcx>,
+        tys: Vec<ty::Ty<'tcx>>,
+    ) -> serde_json::Value {
+        let mut drops = Vec::new();
+        for (idx, ty) in tys.into_iter().enumerate() {
+            if let Some(name) = get_drop_fn_name(mir, ty) {
+                drops.push(json!({ "field": idx, "drop_fn": name }));
+            }
+        }
+        drops.into()
+    }
+
+    let tcx = mir.state.tcx;
+    match *ty.kind() {
+        ty::TyKind::Adt(adt_def, _) if adt_def.is_box() => json!({
+            "kind": "Box",
+            "boxed_drop": get_drop_fn_name(mir, ty.boxed_ty()),
+        }),
+        ty::TyKind::Adt(adt_def, substs) => {
+            // Resolve `<ty as Drop>::drop` rather than using `substs` with the impl's method
+            // directly, since the impl may declare its generics differently from the type (as in
+            // `impl<B, A> Drop for S<A, B>`).
+            let drop_impl = tcx.adt_destructor(adt_def.did()).map(|_| {
+                let drop_trait = tcx.lang_items().drop_trait()
+                    .expect("types with destructors require the Drop lang item");
+                let drop_method = tcx.associated_item_def_ids(drop_trait)[0];
+                get_fn_def_name(mir, drop_method, tcx.mk_substs([ty.into()].iter()))
+            });
+            let variant_field_tys = |v: &ty::VariantDef| v.fields.iter().map(|f| {
+                tcx.subst_and_normalize_erasing_regions(
+                    substs, ty::ParamEnv::reveal_all(), tcx.type_of(f.did))
+            }).collect::<Vec<_>>();
+            match adt_def.adt_kind() {
+                AdtKind::Struct => {
+                    let tys = variant_field_tys(adt_def.non_enum_variant());
+                    json!({
+                        "kind": "Struct",
+                        "drop_impl": drop_impl,
+                        "fields": field_drops(mir, tys),
+                    })
+                },
+                AdtKind::Enum => {
+                    let variants = adt_def.variants().iter()
+                        .map(|v| field_drops(mir, variant_field_tys(v)))
+                        .collect::<Vec<_>>();
+                    json!({
+                        "kind": "Enum",
+                        "drop_impl": drop_impl,
+                        "variants": variants,
+                    })
+                },
+                // Union fields are never dropped implicitly.
+                AdtKind::Union => json!({
+                    "kind": "Union",
+                    "drop_impl": drop_impl,
+                    "fields": [],
+                }),
+            }
+        },
+        ty::TyKind::Tuple(tys) => json!({
+            "kind": "Tuple",
+            "fields": field_drops(mir, tys.iter().collect()),
+        }),
+        ty::TyKind::Closure(_, substs) => json!({
+            "kind": "Closure",
+            "fields": field_drops(mir, substs.as_closure().upvar_tys().collect()),
+        }),
+        ty::TyKind::Array(elem_ty, _) |
+        ty::TyKind::Slice(elem_ty) => json!({
+            "kind": "Array",
+            "elem_drop": get_drop_fn_name(mir, elem_ty),
+        }),
+        _ => json!({ "kind": "Other" }),
+    }
+}
+
 impl ToJson<'_> for hir::def_id::DefId {
     fn to_json(&self, mir: &mut MirState) -> serde_json::Value {
+        if mir.opts.def_id_names {
+            mir.used.def_ids.insert(*self);
+        }
         json!(def_id_str(mir.state.tcx, *self))
     }
 }
@@ -220,6 +522,13 @@ impl ToJson<'_> for hir::def_id::DefId {
 /// rustc's vtables have null entries for non-object-safe methods (those with `Where Self: Sized`).
 /// We omit such methods from our vtables.  This function adjusts vtable indices from rustc's way
 /// of counting to ours.
+///
+/// `tref` must be the principal trait of the `dyn` type, even when calling a supertrait method.
+/// rustc's index for a supertrait method already includes the offset of the supertrait's methods
+/// within the principal's vtable, and `vtable_entries(tref)` lists the supertrait methods (along
+/// with metadata and supertrait vtable pointers, which we also omit) in that same order.  Our
+/// vtables (`build_vtable_items`) and trait definitions (`emit_trait`) are built from the same
+/// list, so the adjusted index is correct for them too.
 fn adjust_method_index<'tcx>(
     tcx: TyCtxt<'tcx>,
     tref: ty::Binder<'tcx, ty::TraitRef<'tcx>>,
@@ -248,23 +557,40 @@ impl<'tcx> ToJson<'tcx> for ty::Instance<'tcx> {
                 "kind": "Intrinsic",
                 "def_id": did.to_json(mir),
                 "substs": substs.to_json(mir),
+                // The bare intrinsic name, such as `size_of` or `simd_add`.
+                "name": mir.state.tcx.item_name(did).as_str(),
             }),
+            // Both of these shims forward to the method itself.  `Instance::resolve` never
+            // produces a shim (only `resolve_for_vtable` and `resolve_for_fn_ptr` do), so it gives
+            // us that target here.
             ty::InstanceDef::VTableShim(did) => json!({
                 "kind": "VTableShim",
                 "def_id": did.to_json(mir),
                 "substs": substs.to_json(mir),
+                "target": get_fn_def_name(mir, did, substs),
             }),
             ty::InstanceDef::ReifyShim(did) => json!({
                 "kind": "ReifyShim",
                 "def_id": did.to_json(mir),
                 "substs": substs.to_json(mir),
+                "target": get_fn_def_name(mir, did, substs),
             }),
-            ty::InstanceDef::FnPtrShim(did, ty) => json!({
-                "kind": "FnPtrShim",
-                "def_id": did.to_json(mir),
-                "substs": substs.to_json(mir),
-                "ty": ty.to_json(mir),
-            }),
+            ty::InstanceDef::FnPtrShim(did, ty) => {
+                // When the shimmed type is a `FnDef`, the shim always calls that function, so we
+                // resolve and emit it here.  For a `FnPtr`, the target is only known at runtime.
+                let target = match *ty.kind() {
+                    ty::TyKind::FnDef(fn_did, fn_substs) =>
+                        Some(get_fn_def_name(mir, fn_did, fn_substs)),
+                    _ => None,
+                };
+                json!({
+                    "kind": "FnPtrShim",
+                    "def_id": did.to_json(mir),
+                    "substs": substs.to_json(mir),
+                    "ty": ty.to_json(mir),
+                    "target": target,
+                })
+            },
             ty::InstanceDef::Virtual(did, idx) => {
                 let self_ty = substs.types().next()
                     .unwrap_or_else(|| panic!("expected self type in substs for {:?}", self));
@@ -276,6 +602,8 @@ impl<'tcx> ToJson<'tcx> for ty::Instance<'tcx> {
                     Some(x) => x,
                     None => panic!("no principal trait for {:?}?", self_ty),
                 };
+                // Use the principal trait, even if `did` is a method of one of its supertraits.
+                // See `adjust_method_index`.
                 let tref = ex_tref.with_self_ty(mir.state.tcx, self_ty);
 
                 let erased_tref = mir.state.tcx.erase_late_bound_regions(tref);
@@ -300,6 +628,7 @@ impl<'tcx> ToJson<'tcx> for ty::Instance<'tcx> {
                 "def_id": did.to_json(mir),
                 "substs": substs.to_json(mir),
                 "ty": ty.to_json(mir),
+                "drops": ty.map(|ty| drop_glue_json(mir, ty)),
             }),
             ty::InstanceDef::CloneShim(did, ty) => {
                 let sub_tys = match *ty.kind() {
@@ -308,7 +637,10 @@ impl<'tcx> ToJson<'tcx> for ty::Instance<'tcx> {
                     ty::TyKind::Closure(closure_did, substs) =>
                         substs.as_closure().upvar_tys().collect(),
                     _ => {
-                        eprintln!("warning: don't know how to build clone shim for {:?}", ty);
+                        let name = def_id_str(mir.state.tcx, did);
+                        mir.report("warning", "unsupported_clone_shim", Some(name), format!(
+                            "don't know how to build clone shim for {:?}", ty));
+                        mir.note_missing("unsupported clone shim");
                         vec![]
                     },
                 };
@@ -350,6 +682,9 @@ impl<'tcx> ToJson<'tcx> for ty::Ty<'tcx> {
 
         // If this type has already been interned, just return its ID.
         if let Some(id) = mir.tys.get(*self) {
+            if mir.opts.flat_types {
+                return json!(mir.tys.index(*self));
+            }
             return json!(id);
         }
 
@@ -380,7 +715,12 @@ impl<'tcx> ToJson<'tcx> for ty::Ty<'tcx> {
                 json!({"kind": "Float", "size": sz.to_json(mir)})
             }
             &ty::TyKind::Array(ref t, ref size) => {
-                json!({"kind": "Array", "ty": t.to_json(mir), "size": size.to_json(mir)})
+                let size = if mir.opts.schema() >= 2 {
+                    array_len_json(mir, *size)
+                } else {
+                    size.to_json(mir)
+                };
+                json!({"kind": "Array", "ty": t.to_json(mir), "size": size})
             }
             &ty::TyKind::Ref(ref _region, ref ty, ref mtbl) => {
                 json!({
@@ -408,9 +748,35 @@ impl<'tcx> ToJson<'tcx> for ty::Ty<'tcx> {
             }
             &ty::TyKind::FnDef(defid, ref substs) => {
                 let name = get_fn_def_name(mir, defid, substs);
+                // Tuple struct and tuple variant names can be used as functions, as in
+                // `iter.map(Some)`.  Record which ADT and variant the constructor builds, so the
+                // consumer can synthesize its body.
+                let ctor = match tcx.def_kind(defid) {
+                    hir::def::DefKind::Ctor(..) => {
+                        let sig = tcx.subst_and_normalize_erasing_regions(
+                            substs, ty::ParamEnv::reveal_all(), tcx.fn_sig(defid));
+                        let out_ty = tcx.erase_late_bound_regions(sig).output();
+                        match *out_ty.kind() {
+                            ty::TyKind::Adt(adt_def, adt_substs) => {
+                                let ai = AdtInst::new(adt_def, adt_substs);
+                                mir.used.types.insert(ai);
+                                Some(json!({
+                                    "adt": adt_inst_id_str(tcx, ai),
+                                    "variant": adt_def.variant_index_with_ctor_id(defid)
+                                        .as_usize(),
+                                }))
+                            },
+                            _ => None,
+                        }
+                    },
+                    _ => None,
+                };
+                let substs = tcx.normalize_erasing_regions(ty::ParamEnv::reveal_all(), *substs);
                 json!({
                     "kind": "FnDef",
                     "defid": name,
+                    "substs": substs.to_json(mir),
+                    "ctor": ctor,
                 })
             }
             &ty::TyKind::Param(..) => unreachable!(
@@ -423,6 +789,7 @@ impl<'tcx> ToJson<'tcx> for ty::Ty<'tcx> {
                         .collect::<Vec<_>>().to_json(mir),
                     // crucible-mir uses the same representation for closures as it does for
                     // tuples, so no additional information is needed.
+                    "fn_impls": closure_fn_impls_json(mir, *self, substs),
                 })
             }
             &ty::TyKind::Dynamic(preds, _region, dynkind) => {
@@ -440,15 +807,29 @@ impl<'tcx> ToJson<'tcx> for ty::Ty<'tcx> {
                             }).collect::<Vec<_>>(),
                         })
                     },
-                    DynKind::DynStar =>
+                    DynKind::DynStar => {
+                        mir.note_missing("dyn* type");
                         json!({
                             "kind": "DynamicStar",
-                        }),
+                        })
+                    },
                 }
             }
-            &ty::TyKind::Alias(ty::AliasKind::Projection, _) => unreachable!(
-                "no TyKind::Alias with AliasKind Projection should remain after monomorphization"
-            ),
+            &ty::TyKind::Alias(ty::AliasKind::Projection, ref proj) => {
+                // Projections that survive monomorphization can usually still be resolved under
+                // `reveal_all`.  If normalization makes progress, emit the concrete type instead.
+                let norm_ty = tcx.try_normalize_erasing_regions(ty::ParamEnv::reveal_all(), *self)
+                    .unwrap_or(*self);
+                if norm_ty != *self {
+                    return norm_ty.to_json(mir);
+                }
+                mir.note_missing("unnormalized projection type");
+                json!({
+                    "kind": "Alias",
+                    "alias_kind": "Projection",
+                    "alias": proj.to_json(mir),
+                })
+            }
             &ty::TyKind::FnPtr(ref sig) => {
                 json!({"kind": "FnPtr", "signature": sig.to_json(mir)})
             }
@@ -460,35 +841,115 @@ impl<'tcx> ToJson<'tcx> for ty::Ty<'tcx> {
             }
             &ty::TyKind::Infer(_) => {
                 // TODO
+                mir.note_missing("Infer type");
                 json!({"kind": "Infer"})
             }
             &ty::TyKind::Bound(_, _) => {
                 // TODO
+                mir.note_missing("Bound type");
                 json!({"kind": "Bound"})
             }
             &ty::TyKind::Placeholder(_) => {
                 // TODO
+                mir.note_missing("Placeholder type");
                 json!({"kind": "Placeholder"})
             }
             &ty::TyKind::Foreign(_) => {
                 // TODO
+                mir.note_missing("Foreign type");
                 json!({"kind": "Foreign"})
             }
-            &ty::TyKind::Generator(_, _, _) => {
-                // TODO
-                json!({"kind": "Generator"})
+            &ty::TyKind::Generator(defid, substs, movability) => {
+                // The generator's own MIR body is its resume function, which is the state
+                // machine that runs the generator up to its next suspend point.
+                let inst = ty::Instance::new(defid, substs);
+                mir.used.instances.insert(inst);
+                let gen = substs.as_generator();
+                let layout = tcx.generator_layout(defid).map(|layout| {
+                    // For each variant (suspend point), the indices of the saved locals that are
+                    // live in that state.  A local saved across several suspend points appears
+                    // in several variants with the same index.
+                    let variant_fields = layout.variant_fields.iter()
+                        .map(|fields| fields.iter().map(|l| l.as_usize()).collect::<Vec<_>>())
+                        .collect::<Vec<_>>();
+                    let variant_tys = gen.state_tys(defid, tcx)
+                        .map(|tys| tys.collect::<Vec<_>>())
+                        .collect::<Vec<_>>();
+                    // The state enum's discriminant selects the point to resume from.  The first
+                    // three states are `Unresumed`, `Returned`, and `Panicked`; each later state
+                    // corresponds to one suspend point, whose location is given by `span`.
+                    let variants = gen.discriminants(defid, tcx)
+                        .map(|(idx, discr)| json!({
+                            "name": ty::GeneratorSubsts::variant_name(idx),
+                            "discr": discr.val.to_string(),
+                            "span": layout.variant_source_info[idx].span.to_json(mir),
+                        }))
+                        .collect::<Vec<_>>();
+                    json!({
+                        "variant_fields": variant_fields,
+                        "variant_tys": variant_tys.iter()
+                            .map(|tys| tys.to_json(mir))
+                            .collect::<Vec<_>>(),
+                        "variants": variants,
+                    })
+                });
+                json!({
+                    "kind": "Generator",
+                    "resume_fn": inst_id_str(tcx, inst),
+                    "movability": movability.to_json(mir),
+                    "upvar_tys": gen.upvar_tys().collect::<Vec<_>>().to_json(mir),
+                    // `Gen` for a plain generator, or `Async(..)` for the state machine of an
+                    // `async` block or function.
+                    "generator_kind": tcx.generator_kind(defid).map(|k| format!("{:?}", k)),
+                    // The type of the argument passed to `Generator::resume`.  This is `()` for
+                    // most plain generators, and `ResumeTy` (which wraps the `&mut Context`
+                    // given to `Future::poll`) for `async` state machines.
+                    "resume_ty": gen.resume_ty().to_json(mir),
+                    "yield_ty": gen.yield_ty().to_json(mir),
+                    "return_ty": gen.return_ty().to_json(mir),
+                    "layout": layout,
+                })
             }
             &ty::TyKind::GeneratorWitness(_) => {
                 // TODO
+                mir.note_missing("GeneratorWitness type");
                 json!({"kind": "GeneratorWitness"})
             }
-            &ty::TyKind::Alias(ty::AliasKind::Opaque, _) => {
-                // TODO
-                json!({"kind": "Alias"})
+            &ty::TyKind::Alias(ty::AliasKind::Opaque, ref alias) => {
+                // As with projections, `reveal_all` normalization replaces an opaque type with
+                // its hidden type, so this fallback should rarely be needed.
+                let norm_ty = tcx.try_normalize_erasing_regions(ty::ParamEnv::reveal_all(), *self)
+                    .unwrap_or(*self);
+                if norm_ty != *self {
+                    return norm_ty.to_json(mir);
+                }
+                mir.note_missing("unnormalized opaque type");
+                json!({
+                    "kind": "Alias",
+                    "alias_kind": "Opaque",
+                    "alias": alias.to_json(mir),
+                })
             }
         };
 
+        let mut j = j;
+        if mir.opts.marker_traits {
+            let penv_ty = ty::ParamEnv::reveal_all().and(*self);
+            j["is_copy"] = tcx.is_copy_raw(penv_ty).into();
+            j["is_sized"] = tcx.is_sized_raw(penv_ty).into();
+            j["is_freeze"] = tcx.is_freeze_raw(penv_ty).into();
+        }
+        if mir.opts.needs_drop {
+            j["needs_drop"] = self.needs_drop(tcx, ty::ParamEnv::reveal_all()).into();
+        }
+        if mir.opts.pretty_types {
+            j["pretty"] = self.to_string().into();
+        }
+
         let id = mir.tys.insert(*self, j);
+        if mir.opts.flat_types {
+            return json!(mir.tys.index(*self));
+        }
         json!(id)
     }
 }
@@ -499,10 +960,124 @@ impl ToJson<'_> for ty::ParamTy {
     }
 }
 
+/// Resolve the `Fn*` trait methods implemented by the closure type `closure_ty`, and mark them
+/// as used.  This links calls through `F: Fn(...)` bounds or `dyn Fn(...)` to the closure's body.
+/// Each entry is `null` if the closure's kind doesn't implement that trait; for example, an
+/// `FnOnce` closure has only `call_once`.
+fn closure_fn_impls_json<'tcx>(
+    mir: &mut MirState<'_, 'tcx>,
+    closure_ty: ty::Ty<'tcx>,
+    substs: ty::subst::SubstsRef<'tcx>,
+) -> serde_json::Value {
+    let tcx = mir.state.tcx;
+    let closure = substs.as_closure();
+    let kind = closure.kind();
+    let sig = tcx.erase_late_bound_regions(closure.sig());
+    let args_ty = sig.inputs()[0];
+    let trait_substs = tcx.intern_substs(&[closure_ty.into(), args_ty.into()]);
+
+    let lang_items = tcx.lang_items();
+    let mut method = |trait_did: Option<DefId>, trait_kind: ty::ClosureKind| {
+        let trait_did = trait_did?;
+        if !kind.extends(trait_kind) {
+            return None;
+        }
+        let method_did = tcx.associated_items(trait_did).in_definition_order()
+            .find(|item| item.kind == ty::AssocKind::Fn)?
+            .def_id;
+        Some(get_fn_def_name(mir, method_did, trait_substs))
+    };
+    json!({
+        "call": method(lang_items.fn_trait(), ty::ClosureKind::Fn),
+        "call_mut": method(lang_items.fn_mut_trait(), ty::ClosureKind::FnMut),
+        "call_once": method(lang_items.fn_once_trait(), ty::ClosureKind::FnOnce),
+    })
+}
+
+/// Render the length of an array type: `{"val": N}` when the length is known, or a reference to
+/// the const parameter for generic lengths like `[T; N]`.
+pub fn array_len_json<'tcx>(mir: &mut MirState<'_, 'tcx>, len: ty::Const<'tcx>) -> serde_json::Value {
+    if let Some(n) = len.try_eval_usize(mir.state.tcx, ty::ParamEnv::reveal_all()) {
+        return json!({"val": n});
+    }
+    match len.kind() {
+        ty::ConstKind::Param(p) => p.to_json(mir),
+        _ => len.to_json(mir),
+    }
+}
+
+impl ToJson<'_> for ty::ParamConst {
+    fn to_json(&self, mir: &mut MirState) -> serde_json::Value {
+        json!({
+            "kind": "ConstParam",
+            "index": self.index,
+            "name": self.name.to_json(mir),
+        })
+    }
+}
+
 impl<'tcx> ToJson<'tcx> for ty::PolyFnSig<'tcx> {
     fn to_json(&self, ms: &mut MirState<'_, 'tcx>) -> serde_json::Value {
         let sig = ms.state.tcx.erase_late_bound_regions(*self);
-        sig.to_json(ms)
+        let mut j = sig.to_json(ms);
+        if ms.opts.late_bound_regions {
+            j["late_bound_regions"] = late_bound_regions_json(*self);
+        }
+        j
+    }
+}
+
+/// Describe the late-bound regions of `sig`, which are erased from its serialized form.  `vars`
+/// lists the variables bound by `sig`'s binder, and `inputs` and `output` list, for each region
+/// that occurs in the corresponding type (in the order `TypeVisitor` visits them), the index in
+/// `vars` of the variable it refers to, or `null` if it isn't bound by this binder.  For example,
+/// `for<'a> fn(&'a u8, &'static u8) -> &'a u8` gives `inputs: [[0], [null]], output: [0]`.
+fn late_bound_regions_json(sig: ty::PolyFnSig) -> serde_json::Value {
+    let vars = sig.bound_vars().iter()
+        .map(|v| match v {
+            ty::BoundVariableKind::Region(ty::BoundRegionKind::BrNamed(_, name)) =>
+                json!({"name": name.as_str()}),
+            ty::BoundVariableKind::Region(ty::BoundRegionKind::BrEnv) => json!({"name": "env"}),
+            _ => json!({"name": null}),
+        })
+        .collect::<Vec<_>>();
+    let regions_of = |ty: ty::Ty| {
+        let mut v = LateBoundRegionUses { depth: ty::INNERMOST, uses: Vec::new() };
+        let _ = ty.visit_with(&mut v);
+        v.uses
+    };
+    let sig = sig.skip_binder();
+    json!({
+        "vars": vars,
+        "inputs": sig.inputs().iter().map(|&ty| regions_of(ty)).collect::<Vec<_>>(),
+        "output": regions_of(sig.output()),
+    })
+}
+
+struct LateBoundRegionUses {
+    /// The binder of the signature, relative to the current position.
+    depth: ty::DebruijnIndex,
+    uses: Vec<Option<u32>>,
+}
+
+impl<'tcx> TypeVisitor<'tcx> for LateBoundRegionUses {
+    fn visit_binder<T: TypeVisitable<'tcx>>(
+        &mut self,
+        t: &ty::Binder<'tcx, T>,
+    ) -> ControlFlow<!> {
+        self.depth.shift_in(1);
+        let _ = t.super_visit_with(self);
+        self.depth.shift_out(1);
+        ControlFlow::Continue(())
+    }
+
+    fn visit_region(&mut self, r: ty::Region<'tcx>) -> ControlFlow<!> {
+        self.uses.push(match *r {
+            ty::RegionKind::ReLateBound(debruijn, br) if debruijn == self.depth =>
+                Some(br.var.as_u32()),
+            _ => None,
+        });
+        ControlFlow::Continue(())
     }
 }
 
@@ -510,18 +1085,29 @@ impl<'tcx> ToJson<'tcx> for ty::FnSig<'tcx> {
     fn to_json(&self, ms: &mut MirState<'_, 'tcx>) -> serde_json::Value {
         let input_jsons : Vec<serde_json::Value> =
             self.inputs().iter().map(|i| i.to_json(ms)).collect();
+        // Under the "rust-call" ABI, the last input is a tuple whose fields are passed as
+        // separate arguments.  Callers must unpack it, as `spread_arg` does for function bodies.
+        let tupled_arg = match self.abi {
+            abi::Abi::RustCall => self.inputs().len().checked_sub(1),
+            _ => None,
+        };
         json!({
             "inputs": input_jsons,
             "output": self.output().to_json(ms),
             "abi": self.abi.to_json(ms),
+            "tupled_arg": tupled_arg,
+            "c_variadic": self.c_variadic,
         })
     }
 }
 
 impl<'tcx> ToJson<'tcx> for ty::TraitRef<'tcx> {
     fn to_json(&self, ms: &mut MirState<'_, 'tcx>) -> serde_json::Value {
+        // A `TraitRef`'s `def_id` always names the trait itself (never an impl or an associated
+        // item), so `trait_name` is a stable key for consumers that model particular traits.
         json!({
             "trait":  self.def_id.to_json(ms),
+            "trait_name": ms.state.tcx.def_path_str(self.def_id),
             "substs":  self.substs.to_json(ms)
         })
     }
@@ -551,9 +1137,20 @@ impl<'tcx> ToJson<'tcx> for ty::Predicate<'tcx> {
                     "projection_ty": pp.projection_ty.to_json(ms),
                     "ty": ty.to_json(ms),
                 }),
-                ty::TermKind::Const(_) => json!("unknown_const_projection"),
+                ty::TermKind::Const(_) => {
+                    ms.note_missing("unknown_const_projection in predicate");
+                    json!("unknown_const_projection")
+                },
+            }
+            ty::PredicateKind::Subtype(sp) => {
+                json!({
+                    "kind": "Subtype",
+                    "a": sp.a.to_json(ms),
+                    "b": sp.b.to_json(ms),
+                })
             }
             _ => {
+                ms.note_missing("unknown_pred in predicate");
                 json!("unknown_pred")
             }
         }
@@ -579,9 +1176,12 @@ impl<'tcx> ToJson<'tcx> for ty::ExistentialPredicate<'tcx> {
                     "substs": proj.substs.to_json(ms),
                     "rhs_ty": ty.to_json(ms),
                 }),
-                ty::TermKind::Const(_) => json!({
-                    "kind": "Projection_Const",
-                }),
+                ty::TermKind::Const(_) => {
+                    ms.note_missing("const projection in trait object");
+                    json!({
+                        "kind": "Projection_Const",
+                    })
+                },
             },
             &ty::ExistentialPredicate::AutoTrait(ref did) => {
                 json!({
@@ -614,11 +1214,29 @@ impl<'tcx> ToJson<'tcx> for ty::GenericPredicates<'tcx> {
     }
 }
 
-impl ToJson<'_> for ty::GenericParamDef {
-    fn to_json(&self, ms: &mut MirState) -> serde_json::Value {
+impl<'tcx> ToJson<'tcx> for ty::GenericParamDef {
+    fn to_json(&self, ms: &mut MirState<'_, 'tcx>) -> serde_json::Value {
+        let tcx = ms.state.tcx;
+        let (kind, default) = match self.kind {
+            ty::GenericParamDefKind::Lifetime => ("Lifetime", None),
+            ty::GenericParamDefKind::Type { .. } => ("Type", None),
+            // A default that mentions other params (`const M: usize = { N + 1 }`) can't be
+            // evaluated here, so we only emit defaults that are closed.
+            ty::GenericParamDefKind::Const { has_default } => {
+                let default = if has_default {
+                    let ct = tcx.const_param_default(self.def_id).0;
+                    if ct.needs_subst() { None } else { Some(ct.to_json(ms)) }
+                } else {
+                    None
+                };
+                ("Const", default)
+            },
+        };
         json!({
             "param_def": *(self.name.as_str()),
             "def_id": self.def_id.to_json(ms),
+            "kind": kind,
+            "default": default,
         }) // TODO
     }
 }
@@ -918,6 +1536,29 @@ impl<'tcx> ToJson<'tcx> for ty::Const<'tcx> {
                     "val": get_const_usize(mir.state.tcx, *self).to_string(),
                 }))
             }
+            // Const params can appear in the types of generic items that are serialized before
+            // monomorphization, such as the field type `[T; N]`.
+            ty::ConstKind::Param(p) => Some(p.to_json(mir)),
+            // rustc already reported an error for this constant, but we may still be able to
+            // produce useful output for the rest of the crate.
+            ty::ConstKind::Error(_) => {
+                mir.report("error", "const_error", None, format!(
+                    "erroneous constant {:?} of type {:?}", self, self.ty()));
+                mir.note_missing("erroneous constant");
+                Some(json!({"kind": "ConstError"}))
+            }
+            ty::ConstKind::Placeholder(_) => {
+                mir.note_missing("placeholder constant");
+                Some(json!({"kind": "ConstPlaceholder"}))
+            }
+            ty::ConstKind::Bound(debruijn, var) => {
+                mir.note_missing("bound constant");
+                Some(json!({
+                    "kind": "ConstBound",
+                    "debruijn": debruijn.as_u32(),
+                    "var": var.as_u32(),
+                }))
+            }
             _ => panic!("don't know how to translate ConstKind::{:?}", self.kind())
         };
         if let Some(rendered) = rendered {
@@ -931,21 +1572,29 @@ impl<'tcx> ToJson<'tcx> for ty::Const<'tcx> {
 impl<'tcx> ToJson<'tcx> for (interpret::ConstValue<'tcx>, ty::Ty<'tcx>) {
     fn to_json(&self, mir: &mut MirState<'_, 'tcx>) -> serde_json::Value {
         let (val, ty) = *self;
-        let op_ty = as_opty(mir.state.tcx, val, ty);
-        let mut icx = interpret::InterpCx::new(
-            mir.state.tcx,
-            DUMMY_SP,
-            ty::ParamEnv::reveal_all(),
-            RenderConstMachine::new(),
-        );
-
         json!({
             "ty": ty.to_json(mir),
-            "rendered": render_opty(mir, &mut icx, &op_ty),
+            "rendered": render_const_value(mir, val, ty),
         })
     }
 }
 
+/// Render the evaluated constant `val` of type `ty`.
+pub fn render_const_value<'tcx>(
+    mir: &mut MirState<'_, 'tcx>,
+    val: interpret::ConstValue<'tcx>,
+    ty: ty::Ty<'tcx>,
+) -> serde_json::Value {
+    let op_ty = as_opty(mir.state.tcx, val, ty);
+    let mut icx = interpret::InterpCx::new(
+        mir.state.tcx,
+        DUMMY_SP,
+        ty::ParamEnv::reveal_all(),
+        RenderConstMachine::new(),
+    );
+    render_opty(mir, &mut icx, &op_ty)
+}
+
 pub fn get_const_usize<'tcx>(tcx: ty::TyCtxt<'tcx>, c: ty::Const<'tcx>) -> usize {
     match c.kind() {
         ty::ConstKind::Value(ty::ValTree::Leaf(val)) => {
@@ -962,6 +1611,7 @@ pub fn render_opty<'mir, 'tcx>(
     op_ty: &interpret::OpTy<'tcx>,
 ) -> serde_json::Value {
     try_render_opty(mir, icx, op_ty).unwrap_or_else(|| {
+        mir.note_missing("unsupported_const");
         json!({
             "kind": "unsupported_const",
             "debug_val": format!("{:?}", op_ty),
@@ -969,6 +1619,25 @@ pub fn render_opty<'mir, 'tcx>(
     })
 }
 
+/// Render the value of an integer constant.  `bits` holds the raw (unextended) bits of the
+/// value, and `text` its decimal form.  With `--split-wide-ints`, 128-bit values are emitted as
+/// their high and low 64-bit words, so consumers don't need to parse the decimal form exactly.
+fn int_val_json(
+    mir: &MirState<'_, '_>,
+    bits: u128,
+    size: Size,
+    text: String,
+) -> serde_json::Value {
+    if mir.opts.split_wide_ints && size.bits() == 128 {
+        json!({
+            "hi": (bits >> 64) as u64,
+            "lo": bits as u64,
+        })
+    } else {
+        text.into()
+    }
+}
+
 pub fn try_render_opty<'mir, 'tcx>(
     mir: &mut MirState<'_, 'tcx>,
     icx: &mut interpret::InterpCx<'mir, 'tcx, RenderConstMachine<'mir, 'tcx>>,
@@ -996,7 +1665,7 @@ pub fn try_render_opty<'mir, 'tcx>(
                     _ => unreachable!(),
                 },
                 "size": size.bytes(),
-                "val": bits.to_string(),
+                "val": int_val_json(mir, bits, size, bits.to_string()),
             })
         }
         ty::TyKind::Int(i) => {
@@ -1016,7 +1685,7 @@ pub fn try_render_opty<'mir, 'tcx>(
                     _ => unreachable!(),
                 },
                 "size": size.bytes(),
-                "val": val.to_string(),
+                "val": int_val_json(mir, bits, size, val.to_string()),
             })
         }
         ty::TyKind::Float(fty) => {
@@ -1049,7 +1718,7 @@ pub fn try_render_opty<'mir, 'tcx>(
             })
         },
         ty::TyKind::Adt(adt_def, _substs) if adt_def.is_enum() => {
-            let (_, variant_idx) = icx.read_discriminant(&op_ty).unwrap();
+            let (discr, variant_idx) = icx.read_discriminant(&op_ty).unwrap();
             let val = icx.operand_downcast(op_ty, variant_idx).unwrap();
             let mut field_vals = Vec::with_capacity(val.layout.fields.count());
             for idx in 0 .. val.layout.fields.count() {
@@ -1057,10 +1726,25 @@ pub fn try_render_opty<'mir, 'tcx>(
                 field_vals.push(try_render_opty(mir, icx,  &field_opty)?);
             }
 
+            // The discriminant has the enum's `#[repr]` integer type (`isize` if there is none),
+            // which may differ from the type of the tag stored in memory.
+            let discr_ty = ty.discriminant_ty(tcx);
+            let discr_size = tcx.layout_of(ty::ParamEnv::reveal_all().and(discr_ty)).ok()?.size;
+            let discr_bits = discr.to_bits(discr_size).ok()?;
+            let discr_text = if discr_ty.is_signed() {
+                (discr_size.sign_extend(discr_bits) as i128).to_string()
+            } else {
+                discr_bits.to_string()
+            };
+
             json!({
                 "kind": "enum",
                 "variant": variant_idx.as_u32(),
                 "fields": field_vals,
+                "discr": {
+                    "ty": discr_ty.to_json(mir),
+                    "val": int_val_json(mir, discr_bits, discr_size, discr_text),
+                },
             })
         },
 
@@ -1201,9 +1885,10 @@ fn try_render_ref_opty<'mir, 'tcx>(
                     ()
                 }
             },
-            // Special case for &[T]
+            // Special case for &[T].  Unlike `&str`, the elements can have any type (such as
+            // `&[u16]` or `&[SomeStruct]`), so we render each one using its own layout.  The
+            // length comes from the fat pointer's metadata, in elements rather than bytes.
             ty::TyKind::Slice(slice_ty) => {
-                eprintln!("slice op_ty: {:?}", op_ty);
                 let slice_len = mplace_ty_len(&d, icx).unwrap();
                 let mut elt_values = Vec::with_capacity(slice_len as usize);
                 for idx in 0..slice_len {
@@ -1221,14 +1906,20 @@ fn try_render_ref_opty<'mir, 'tcx>(
         }
     }
 
-    let (prov, _offset) = d.ptr.into_parts();
+    let (prov, offset) = d.ptr.into_parts();
     let alloc = tcx.try_get_global_alloc(prov?)?;
     match alloc {
-        interpret::GlobalAlloc::Static(def_id) =>
+        interpret::GlobalAlloc::Static(def_id) => {
+            mir.used.statics.insert(def_id);
+            // The reference may point into the middle of the static, as in `&S.1`.  `offset` is
+            // the distance in bytes from the start of the static to the referenced value.
             return Some(json!({
                 "kind": "static_ref",
                 "def_id": def_id.to_json(mir),
-            })),
+                "thread_local": tcx.is_thread_local_static(def_id),
+                "offset": offset.bytes(),
+            }));
+        },
         interpret::GlobalAlloc::Memory(ca) => {
             let ty = op_ty.layout.ty;
             let aid = match mir.allocs.get(ca, ty) {
@@ -1255,6 +1946,7 @@ fn try_render_ref_opty<'mir, 'tcx>(
             return Some(json!({
                 "kind": "static_ref",
                 "def_id": aid,
+                "thread_local": false,
             }));
         }
         _ => return None
@@ -1390,70 +2082,211 @@ impl<'tcx> ToJson<'tcx> for AdtInst<'tcx> {
 
         let kind = match self.adt.adt_kind() {
             AdtKind::Struct => json!({"kind": "Struct"}),
-            AdtKind::Enum =>
-                json!({
+            AdtKind::Enum => {
+                // `discr_ty` is the type of the discriminant values (as seen by
+                // `Rvalue::Discriminant`), while `tag_ty` is the integer type of the tag actually
+                // stored in memory, which rustc may shrink to the smallest type that fits.
+                // Enums with at most one inhabited variant have no tag, and no `tag_ty` field.
+                let tag_ty = match tyl.variants {
+                    Variants::Single { .. } => None,
+                    Variants::Multiple { ref tag, .. } =>
+                        Some(tag.primitive().to_int_ty(mir.state.tcx)),
+                };
+                let mut j = json!({
                     "kind": "Enum",
                     "discr_ty": self.adt
                                     .repr()
                                     .discr_type()
                                     .to_ty(mir.state.tcx)
-                                    .to_json(mir)
-                }),
+                                    .to_json(mir),
+                    // `repr(C)` and `repr(u8)` etc. fix the layout as a tag followed by a union of
+                    // the variants' payloads, so such enums always use a `Direct` encoding.
+                    "repr_c": self.adt.repr().c(),
+                    "repr_int": self.adt.repr().int.is_some(),
+                });
+                if let Some(tag_ty) = tag_ty {
+                    j["tag_ty"] = tag_ty.to_json(mir);
+                }
+                if let Variants::Multiple { ref tag_encoding, tag_field, .. } = tyl.variants {
+                    // The tag is field `tag_field` of the enum's own layout.  The payload fields
+                    // of each variant have their own offsets, given in `variants`.
+                    j["tag_offset"] = tyl.fields.offset(tag_field).bytes().into();
+                    match *tag_encoding {
+                        TagEncoding::Direct => {
+                            j["tag_encoding"] = "Direct".into();
+                        },
+                        // Variants other than `untagged_variant` are encoded by storing
+                        // `niche_start + (variant - niche_variants.start)` (wrapping) in the tag,
+                        // which lives in otherwise-invalid values of a field of
+                        // `untagged_variant`.
+                        TagEncoding::Niche { untagged_variant, ref niche_variants, niche_start } => {
+                            j["tag_encoding"] = "Niche".into();
+                            j["untagged_variant"] = untagged_variant.as_u32().into();
+                            j["niche_variants"] = json!({
+                                "start": niche_variants.start().as_u32(),
+                                "end": niche_variants.end().as_u32(),
+                            });
+                            j["niche_start"] = niche_start.to_string().into();
+                        },
+                    }
+                }
+                j
+            },
+            // A union has a single variant, but its fields overlap rather than being laid out
+            // in sequence: every field's `offset` is 0, and `size` covers the largest field
+            // (rounded up to the union's alignment).
             AdtKind::Union => json!({"kind": "Union"}),
         };
 
+        // `repr(simd)` types have either a single array field or several fields of the same
+        // type.  `simd_size_and_type` handles both forms.
+        let simd = if self.adt.repr().simd() {
+            let (lanes, elem_ty) = ty.simd_size_and_type(mir.state.tcx);
+            Some(json!({
+                "elem_ty": elem_ty.to_json(mir),
+                "lanes": lanes,
+            }))
+        } else {
+            None
+        };
+
+        // Types like `NonZeroU8` restrict the values of their scalar field with
+        // `#[rustc_layout_scalar_valid_range_start/end]`.  Both bounds are inclusive, and are
+        // rendered as strings since they may exceed 64 bits.
+        let scalar_valid_range = match mir.state.tcx.layout_scalar_valid_range(self.adt.did()) {
+            (Bound::Unbounded, Bound::Unbounded) => None,
+            (start, end) => {
+                let bound_json = |b: Bound<u128>| match b {
+                    Bound::Included(x) => Some(x.to_string()),
+                    Bound::Excluded(_) => unreachable!("scalar valid ranges are inclusive"),
+                    Bound::Unbounded => None,
+                };
+                Some(json!({
+                    "start": bound_json(start),
+                    "end": bound_json(end),
+                }))
+            },
+        };
+
+        // A `repr(transparent)` type has the same layout as its one non-zero-sized field.
+        let transparent_field = if self.adt.repr().transparent() {
+            let tcx = mir.state.tcx;
+            self.adt.variants().iter().next().and_then(|v| {
+                v.fields.iter().position(|f| {
+                    let fty = tcx.subst_and_normalize_erasing_regions(
+                        self.substs, ty::ParamEnv::reveal_all(), tcx.type_of(f.did));
+                    tcx.layout_of(ty::ParamEnv::reveal_all().and(fty))
+                        .map_or(true, |l| !l.is_zst())
+                })
+            })
+        } else {
+            None
+        };
+
+        // `PhantomData<T>` holds no `T`, but still acts as one for variance, auto traits, and drop
+        // checking.
+        let phantom_ty = if self.adt.is_phantom_data() {
+            Some(self.substs.type_at(0).to_json(mir))
+        } else {
+            None
+        };
+
         let variants =
             if self.adt.is_enum() {
-                render_enum_variants(mir, &self)
+                render_enum_variants(mir, &self, &tyl)
             } else {
                 self.adt.variants()
-                        .iter()
-                        .map(|v| render_variant(mir, &self, v, &None))
+                        .iter_enumerated()
+                        .map(|(idx, v)| {
+                            let shape = variant_fields_shape(&tyl, idx);
+                            render_variant(mir, &self, v, &None, shape.as_ref())
+                        })
                         .collect::<Vec<serde_json::Value>>()
                         .into()
             };
 
-        json!({
+        let mut j = json!({
             "name": adt_inst_id_str(mir.state.tcx, *self),
+            "crate": crate_json(mir.state.tcx, self.adt.did()),
             "kind": kind,
             "variants": variants,
             "size": tyl.size.bytes(),
+            "is_zst": tyl.is_zst(),
+            "phantom_ty": phantom_ty,
             "repr_transparent": self.adt.repr().transparent(),
+            "transparent_field": transparent_field,
+            "scalar_valid_range": scalar_valid_range,
+            "simd": simd,
+            // For `repr(packed(N))`, fields are aligned to at most `N` bytes, so a field's
+            // `offset` may not be a multiple of its type's natural alignment.
+            "packed": self.adt.repr().pack.map(|a| a.bytes()),
             "orig_def_id": self.adt.did().to_json(mir),
             "orig_substs": self.substs.to_json(mir),
-        })
+        });
+        if mir.opts.variances {
+            j["variances"] = variances_json(mir.state.tcx, self.adt.did());
+        }
+        j
     }
 }
 
 fn render_enum_variants<'tcx>(
     mir: &mut MirState<'_, 'tcx>,
     adt: &AdtInst<'tcx>,
+    tyl: &TyAndLayout<'tcx>,
 ) -> serde_json::Value {
     let mut variants = Vec::with_capacity(adt.adt.variants().len());
     for (idx, d_value) in adt.adt.discriminants(mir.state.tcx) {
         let v = adt.adt.variant(idx);
-        let rendered = render_variant(mir, adt, v, &Some(d_value.to_string()));
+        let shape = variant_fields_shape(tyl, idx);
+        let rendered = render_variant(mir, adt, v, &Some(d_value.to_string()), shape.as_ref());
         variants.push(rendered);
     }
 
     variants.into()
 }
 
+/// Get the layout of the fields of variant `idx` of `tyl`.  Returns `None` if the variant has
+/// no layout of its own, which happens for uninhabited variants of single-variant layouts.
+fn variant_fields_shape(tyl: &TyAndLayout<'_>, idx: VariantIdx) -> Option<FieldsShape> {
+    match tyl.variants {
+        Variants::Single { index } if index == idx => Some(tyl.fields.clone()),
+        Variants::Single { .. } => None,
+        Variants::Multiple { ref variants, .. } => Some(variants[idx].fields.clone()),
+    }
+}
+
 fn render_variant<'tcx>(
     mir: &mut MirState<'_, 'tcx>,
     adt: &AdtInst<'tcx>,
     v: &ty::VariantDef,
-    mb_discr: &Option<String>
+    mb_discr: &Option<String>,
+    shape: Option<&FieldsShape>,
 ) -> serde_json::Value {
     let tcx = mir.state.tcx;
     let inhabited = v.inhabited_predicate(tcx, adt.adt)
                      .subst(tcx, adt.substs)
                      .apply_ignore_module(tcx, ty::ParamEnv::reveal_all());
 
+    let mut fields = Vec::with_capacity(v.fields.len());
+    for (i, f) in v.fields.iter().enumerate() {
+        let mut j = f.tojson(mir, adt.substs);
+        // The offset of an unsized field depends on the alignment of the dynamic value, so we
+        // omit it in that case.
+        let fty = tcx.subst_and_normalize_erasing_regions(
+            adt.substs, ty::ParamEnv::reveal_all(), tcx.type_of(f.did));
+        if let Some(shape) = shape {
+            if tcx.is_sized_raw(ty::ParamEnv::reveal_all().and(fty)) {
+                j["offset"] = shape.offset(i).bytes().into();
+            }
+        }
+        fields.push(j);
+    }
+
     json!({
         "name": v.def_id.to_json(mir),
         "discr": v.discr.to_json(mir),
-        "fields": v.fields.tojson(mir, adt.substs),
+        "fields": fields,
         "ctor_kind": v.ctor_kind().to_json(mir),
         "discr_value": mb_discr,
         "inhabited": inhabited,
@@ -1471,6 +2304,9 @@ impl ToJsonAg for ty::FieldDef {
             substs, ty::ParamEnv::reveal_all(), unsubst_ty);
         json!({
             "name": self.did.to_json(mir),
+            // The source-level name of the field.  For tuple-like structs and variants, rustc
+            // names the fields by their position (`0`, `1`, ...).
+            "ident": self.name.as_str(),
             "ty": ty.to_json(mir),
         })
     }
@@ -1484,6 +2320,8 @@ pub fn handle_adt_ag<'tcx>(
     match ak {
         &mir::AggregateKind::Adt(adt_did, variant, substs, _, _) => {
             let adt = mir.state.tcx.adt_def(adt_did);
+            // For `repr(simd)` types, the lane count and element type are given by the `simd`
+            // field of `adt`, and there is one operand per field.
             json!({
                 "adt": AdtInst::new(adt, substs).to_json(mir),
                 "variant": variant.to_json(mir),
@@ -16,6 +16,7 @@ extern crate rustc_log;
 use std::env;
 use std::fs::File;
 use std::io;
+use std::process;
 use std::time::Instant;
 use mir_json::link;
 
@@ -35,6 +36,9 @@ fn main() {
     let mut inputs = env::args().skip(1).map(|arg| File::open(&arg))
         .collect::<io::Result<Vec<_>>>().unwrap();
     let output = io::BufWriter::new(io::stdout());
-    link::link_crates(&mut inputs, output).unwrap();
+    if let Err(e) = link::link_crates(&mut inputs, output) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
     debug!("{:?}: link crates", measure());
 }
@@ -25,13 +25,13 @@ use rustc_session::config::ExternLocation;
 use std::collections::HashSet;
 use std::env;
 use std::ffi::OsStr;
-use std::fs::{File, OpenOptions, read_dir};
+use std::fs::{self, File, OpenOptions, read_dir};
 use std::io::{self, Write};
 use std::iter;
 use std::os::unix::fs::OpenOptionsExt;
 use std::os::unix::process::CommandExt;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{self, Command};
 
 
 /// Driver callbacks that get the output filename and then stop compilation.  This is used to get
@@ -104,6 +104,7 @@ struct MirJsonCallbacks {
     analysis_data: Option<analyz::AnalysisData<()>>,
     use_override_crates: HashSet<String>,
     export_style: analyz::ExportStyle,
+    options: analyz::Options,
 }
 
 impl rustc_driver::Callbacks for MirJsonCallbacks {
@@ -134,7 +135,8 @@ impl rustc_driver::Callbacks for MirJsonCallbacks {
         compiler: &Compiler,
         queries: &'tcx Queries<'tcx>,
     ) -> Compilation {
-        self.analysis_data = analyz::analyze(compiler.session(), queries, self.export_style).unwrap();
+        self.analysis_data = analyz::analyze(
+            compiler.session(), queries, self.export_style, &self.options).unwrap();
         Compilation::Continue
     }
 }
@@ -144,7 +146,12 @@ fn link_mirs(main_path: PathBuf, extern_paths: &[PathBuf], out_path: &Path) {
         .map(File::open)
         .collect::<io::Result<Vec<_>>>().unwrap();
     let output = io::BufWriter::new(File::create(out_path).unwrap());
-    link::link_crates(&mut inputs, output).unwrap();
+    if let Err(e) = link::link_crates(&mut inputs, output) {
+        // Don't leave a partial output behind for later steps to pick up.
+        let _ = fs::remove_file(out_path);
+        eprintln!("error: failed to link {}: {}", out_path.display(), e);
+        process::exit(1);
+    }
 }
 
 fn write_test_script(script_path: &Path, json_path: &Path) -> io::Result<()> {
@@ -159,6 +166,9 @@ fn write_test_script(script_path: &Path, json_path: &Path) -> io::Result<()> {
 fn go() {
     // First arg is the name of the `rustc` binary that cargo means to invoke, which we ignore.
     let mut args: Vec<String> = std::env::args().skip(1).collect();
+    // Strip out mir-json flags first, so they don't get passed to `rustc` in any of the modes
+    // below.
+    let options = analyz::Options::take_from_args(&mut args);
 
     // XXX big hack: We need to use normal rustc (with its normal libs) for `build.rs` scripts,
     // since our custom libs aren't actually functional.  To distinguish `build.rs` and `build.rs`
@@ -214,6 +224,7 @@ fn go() {
                     analysis_data: None,
                     use_override_crates: use_override_crates.clone(),
                     export_style,
+                    options,
                 },
             ).run().unwrap();
             return;
@@ -225,6 +236,13 @@ fn go() {
     // for all its dependencies, and produce a test script (in place of the test binary expected by
     // cargo) that will run `crux-mir` on the linked JSON file.
 
+    // JSON Lines output has no index, so it can't be linked.  Reject it now, rather than failing
+    // to parse it after the build.
+    if options.json_lines {
+        eprintln!("error: --json-lines can't be used for --test builds, which must be linked");
+        process::exit(1);
+    }
+
     // We're still using the original args (with only a few modifications), so the output path
     // should be the path of the test binary.
     eprintln!("test build - extract output path - {:?}", args);
@@ -248,8 +266,13 @@ fn go() {
         analysis_data: None,
         use_override_crates: use_override_crates.clone(),
         export_style,
+        options,
     };
     rustc_driver::RunCompiler::new(&args, &mut callbacks).run().unwrap();
+    // `--list-missing` only prints its report, so there is no `.mir` file to link.
+    if callbacks.options.list_missing {
+        return;
+    }
     let data = callbacks.analysis_data
         .expect("failed to find main MIR path");
 
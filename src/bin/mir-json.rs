@@ -20,6 +20,7 @@ use std::env;
 
 struct MirJsonCallbacks {
     export_style: analyz::ExportStyle,
+    options: analyz::Options,
 }
 
 impl rustc_driver::Callbacks for MirJsonCallbacks {
@@ -48,13 +49,14 @@ impl rustc_driver::Callbacks for MirJsonCallbacks {
         compiler: &Compiler,
         queries: &'tcx Queries<'tcx>,
     ) -> Compilation {
-        analyz::analyze(compiler.session(), queries, self.export_style).unwrap();
+        analyz::analyze(compiler.session(), queries, self.export_style, &self.options).unwrap();
         Compilation::Continue
     }
 }
 
 fn go() {
-    let args: Vec<String> = std::env::args().collect();
+    let mut args: Vec<String> = std::env::args().collect();
+    let options = analyz::Options::take_from_args(&mut args);
 
     let export_style = if env::var("EXPORT_ALL").is_ok() {
         analyz::ExportStyle::ExportAll
@@ -62,7 +64,8 @@ fn go() {
         analyz::ExportStyle::ExportCruxTests
     };
 
-    rustc_driver::RunCompiler::new(&args, &mut MirJsonCallbacks { export_style }).run().unwrap();
+    rustc_driver::RunCompiler::new(&args, &mut MirJsonCallbacks { export_style, options })
+        .run().unwrap();
 }
 
 fn main() {
@@ -8,6 +8,7 @@ extern crate serde_cbor;
 extern crate tar;
 
 extern crate rustc_ast;
+extern crate rustc_attr;
 extern crate rustc_const_eval;
 extern crate rustc_driver;
 extern crate rustc_data_structures;
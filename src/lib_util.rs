@@ -21,6 +21,7 @@ use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{self, Read, Write, Seek, SeekFrom, Cursor, BufWriter};
+use std::mem;
 use std::path::Path;
 
 use serde_json::Value as JsonValue;
@@ -39,6 +40,27 @@ pub struct CrateIndex {
     pub items: HashMap<StringId, ItemData>,
 
     pub roots: Vec<StringId>,
+
+    /// Description of the target the crate was compiled for (see `analyz::target_json`).  This
+    /// is kept in the index, rather than in `crate.json`, so that `link` can copy it to the output
+    /// without parsing the crate.
+    #[serde(default)]
+    pub target: Option<JsonValue>,
+
+    /// The functions backing the global allocator (see `analyz::allocator_json`), if this crate
+    /// selects one.  Like `target`, this is kept in the index for use by `link`.
+    #[serde(default)]
+    pub allocator: Option<JsonValue>,
+
+    /// The panic strategy the crate was compiled with, either `"unwind"` or `"abort"`.
+    #[serde(default)]
+    pub panic_strategy: Option<String>,
+
+    /// The version of the output format the crate was emitted in (see
+    /// `analyz::SCHEMA_VERSION`).  This is `None` for crates emitted before the version
+    /// was recorded, which use version 1.
+    #[serde(default)]
+    pub schema: Option<u32>,
 }
 
 /// Metadata about a single item.
@@ -66,6 +88,8 @@ pub enum EntryKind {
     Trait,
     Intrinsic,
     Ty,
+    DefIdName,
+    TraitAlias,
 }
 
 impl EntryKind {
@@ -79,6 +103,8 @@ impl EntryKind {
             Trait => "trait",
             Intrinsic => "intrinsic",
             Ty => "ty",
+            DefIdName => "def_id_name",
+            TraitAlias => "trait_alias",
         }
     }
 
@@ -92,6 +118,8 @@ impl EntryKind {
             Trait => "traits",
             Intrinsic => "intrinsics",
             Ty => "tys",
+            DefIdName => "def_id_names",
+            TraitAlias => "trait_aliases",
         }
     }
 
@@ -99,12 +127,12 @@ impl EntryKind {
         use self::EntryKind::*;
         // Type annotation ensures we have exactly `count()` entries.
         let all: &'static [EntryKind; Self::count()] =
-            &[Fn, Adt, Static, Vtable, Trait, Intrinsic, Ty];
+            &[Fn, Adt, Static, Vtable, Trait, Intrinsic, Ty, DefIdName, TraitAlias];
         all.iter().cloned()
     }
 
     pub const fn count() -> usize {
-        7
+        9
     }
 }
 
@@ -169,6 +197,10 @@ struct EmitterState {
     dep_map: HashMap<StringId, HashSet<StringId>>,
     entry_loc: HashMap<(StringId, EntryKind), (u64, u64)>,
     roots: HashSet<StringId>,
+    target: Option<JsonValue>,
+    allocator: Option<JsonValue>,
+    panic_strategy: Option<String>,
+    schema: Option<u32>,
     intern: InternTable,
 }
 
@@ -245,7 +277,15 @@ impl EmitterState {
         let mut roots = self.roots.into_iter().collect::<Vec<_>>();
         roots.sort();
 
-        CrateIndex { names, items, roots }
+        CrateIndex {
+            names,
+            items,
+            roots,
+            target: self.target,
+            allocator: self.allocator,
+            panic_strategy: self.panic_strategy,
+            schema: self.schema,
+        }
     }
 }
 
@@ -308,6 +348,28 @@ impl<W: Write> Emitter<W> {
         write!(self.writer, ",")?;
         self.emit_table_from(EntryKind::Intrinsic, j)?;
         write!(self.writer, ",")?;
+        self.emit_table_from(EntryKind::DefIdName, j)?;
+        write!(self.writer, ",")?;
+        self.emit_table_from(EntryKind::TraitAlias, j)?;
+        write!(self.writer, ",")?;
+        write!(self.writer, "\"target\":")?;
+        serde_json::to_writer(&mut self.writer, &j["target"])?;
+        self.state.target = Some(j["target"].clone());
+        write!(self.writer, ",")?;
+        write!(self.writer, "\"allocator\":")?;
+        serde_json::to_writer(&mut self.writer, &j["allocator"])?;
+        if !j["allocator"].is_null() {
+            self.state.allocator = Some(j["allocator"].clone());
+        }
+        write!(self.writer, ",")?;
+        write!(self.writer, "\"panic_strategy\":")?;
+        serde_json::to_writer(&mut self.writer, &j["panic_strategy"])?;
+        self.state.panic_strategy = j["panic_strategy"].as_str().map(|s| s.to_owned());
+        write!(self.writer, ",")?;
+        write!(self.writer, "\"schema\":")?;
+        serde_json::to_writer(&mut self.writer, &j["schema"])?;
+        self.state.schema = j["schema"].as_u64().map(|n| n as u32);
+        write!(self.writer, ",")?;
         write!(self.writer, "\"roots\":")?;
         serde_json::to_writer(&mut self.writer, &j["roots"])?;
         write!(self.writer, "}}")?;
@@ -391,6 +453,15 @@ pub fn read_crate_index<R: Read + Seek>(mut input: R) -> serde_cbor::Result<(Cra
 pub trait JsonOutput {
     fn emit(&mut self, kind: EntryKind, j: serde_json::Value) -> io::Result<()>;
     fn add_root(&mut self, name: String) -> io::Result<()>;
+    /// Record the description of the target the crate was compiled for.
+    fn set_target(&mut self, j: serde_json::Value) -> io::Result<()>;
+    /// Record the functions that back the global allocator.  This is only called for crates that
+    /// select an allocator, such as executables.
+    fn set_allocator(&mut self, j: serde_json::Value) -> io::Result<()>;
+    /// Record the panic strategy the crate was compiled with (`"unwind"` or `"abort"`).
+    fn set_panic_strategy(&mut self, s: &str) -> io::Result<()>;
+    /// Record the version of the output format being emitted.
+    fn set_schema(&mut self, version: u32) -> io::Result<()>;
 }
 
 #[derive(Default)]
@@ -416,6 +487,20 @@ pub struct Output {
     /// key into this table.  This encoding avoids exponential blowup when large types appear
     /// repeatedly within a crate.
     pub tys: Vec<serde_json::Value>,
+    /// Human-readable paths for the `DefId`s named in this crate's output.  This is only
+    /// populated with `--def-id-names`, and is meant for debugging.
+    pub def_id_names: Vec<serde_json::Value>,
+    /// Trait aliases (`trait Foo = Bar + Baz;`) defined in the crate, with the traits they expand
+    /// to.
+    pub trait_aliases: Vec<serde_json::Value>,
+    /// The target this crate was compiled for.
+    pub target: serde_json::Value,
+    /// The global allocator functions, or `null` if this crate doesn't select an allocator.
+    pub allocator: serde_json::Value,
+    /// The panic strategy this crate was compiled with.
+    pub panic_strategy: String,
+    /// The version of the output format.
+    pub schema: u32,
     /// Entry points for this crate.
     pub roots: Vec<String>,
 }
@@ -430,6 +515,8 @@ impl JsonOutput for Output {
             EntryKind::Trait => self.traits.push(j),
             EntryKind::Intrinsic => self.intrinsics.push(j),
             EntryKind::Ty => self.tys.push(j),
+            EntryKind::DefIdName => self.def_id_names.push(j),
+            EntryKind::TraitAlias => self.trait_aliases.push(j),
         }
         Ok(())
     }
@@ -438,6 +525,57 @@ impl JsonOutput for Output {
         self.roots.push(name);
         Ok(())
     }
+
+    fn set_target(&mut self, j: serde_json::Value) -> io::Result<()> {
+        self.target = j;
+        Ok(())
+    }
+
+    fn set_allocator(&mut self, j: serde_json::Value) -> io::Result<()> {
+        self.allocator = j;
+        Ok(())
+    }
+
+    fn set_panic_strategy(&mut self, s: &str) -> io::Result<()> {
+        self.panic_strategy = s.to_owned();
+        Ok(())
+    }
+
+    fn set_schema(&mut self, version: u32) -> io::Result<()> {
+        self.schema = version;
+        Ok(())
+    }
+}
+
+/// Output that discards everything written to it.  This is used for `--list-missing`, which
+/// translates the crate only to find the constructs it can't handle.
+#[derive(Default)]
+pub struct NullOutput;
+
+impl JsonOutput for NullOutput {
+    fn emit(&mut self, _kind: EntryKind, _j: serde_json::Value) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn add_root(&mut self, _name: String) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn set_target(&mut self, _j: serde_json::Value) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn set_allocator(&mut self, _j: serde_json::Value) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn set_panic_strategy(&mut self, _s: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn set_schema(&mut self, _version: u32) -> io::Result<()> {
+        Ok(())
+    }
 }
 
 
@@ -490,6 +628,27 @@ impl<W: Write> JsonOutput for StreamingEmitter<W> {
         self.inner.add_root(name.into());
         Ok(())
     }
+
+    fn set_target(&mut self, j: serde_json::Value) -> io::Result<()> {
+        // The streaming format has no place for crate-level data, so this goes only in the index.
+        self.inner.state.target = Some(j);
+        Ok(())
+    }
+
+    fn set_allocator(&mut self, j: serde_json::Value) -> io::Result<()> {
+        self.inner.state.allocator = Some(j);
+        Ok(())
+    }
+
+    fn set_panic_strategy(&mut self, s: &str) -> io::Result<()> {
+        self.inner.state.panic_strategy = Some(s.to_owned());
+        Ok(())
+    }
+
+    fn set_schema(&mut self, version: u32) -> io::Result<()> {
+        self.inner.state.schema = Some(version);
+        Ok(())
+    }
 }
 
 
@@ -507,6 +666,22 @@ impl JsonOutput for MirStream {
     fn add_root(&mut self, name: String) -> io::Result<()> {
         self.emitter.add_root(name)
     }
+
+    fn set_target(&mut self, j: serde_json::Value) -> io::Result<()> {
+        self.emitter.set_target(j)
+    }
+
+    fn set_allocator(&mut self, j: serde_json::Value) -> io::Result<()> {
+        self.emitter.set_allocator(j)
+    }
+
+    fn set_panic_strategy(&mut self, s: &str) -> io::Result<()> {
+        self.emitter.set_panic_strategy(s)
+    }
+
+    fn set_schema(&mut self, version: u32) -> io::Result<()> {
+        self.emitter.set_schema(version)
+    }
 }
 
 fn make_tar_entry(path: &str) -> io::Result<tar::Header> {
@@ -537,3 +712,65 @@ pub fn finish_streaming(ms: MirStream) -> serde_cbor::Result<()> {
     w.flush()?;
     Ok(())
 }
+
+
+// JSON Lines output.
+
+/// Unindexed output in JSON Lines format: each entry is written on its own line, as a
+/// `{"kind": ..., "data": ...}` object, as soon as it is produced.  The roots are written on a
+/// final `{"kind": "roots", ...}` line.  This format can't be linked with other crates, but it can
+/// be consumed incrementally, even for crates whose full JSON would be too large to hold in
+/// memory.
+pub struct JsonLinesOutput {
+    w: BufWriter<File>,
+    roots: Vec<String>,
+}
+
+impl JsonOutput for JsonLinesOutput {
+    fn emit(&mut self, kind: EntryKind, j: serde_json::Value) -> io::Result<()> {
+        self.emit_line(kind.name(), &j)
+    }
+
+    fn add_root(&mut self, name: String) -> io::Result<()> {
+        self.roots.push(name);
+        Ok(())
+    }
+
+    fn set_target(&mut self, j: serde_json::Value) -> io::Result<()> {
+        self.emit_line("target", &j)
+    }
+
+    fn set_allocator(&mut self, j: serde_json::Value) -> io::Result<()> {
+        self.emit_line("allocator", &j)
+    }
+
+    fn set_panic_strategy(&mut self, s: &str) -> io::Result<()> {
+        self.emit_line("panic_strategy", &s.into())
+    }
+
+    fn set_schema(&mut self, version: u32) -> io::Result<()> {
+        self.emit_line("schema", &version.into())
+    }
+}
+
+impl JsonLinesOutput {
+    fn emit_line(&mut self, kind: &str, j: &serde_json::Value) -> io::Result<()> {
+        write!(self.w, r#"{{"kind":"{}","data":"#, kind)?;
+        serde_json::to_writer(&mut self.w, j)?;
+        writeln!(self.w, "}}")?;
+        Ok(())
+    }
+}
+
+pub fn start_json_lines(path: &Path) -> io::Result<JsonLinesOutput> {
+    Ok(JsonLinesOutput {
+        w: BufWriter::new(File::create(path)?),
+        roots: Vec::new(),
+    })
+}
+
+pub fn finish_json_lines(mut out: JsonLinesOutput) -> io::Result<()> {
+    let roots = mem::take(&mut out.roots);
+    out.emit_line("roots", &roots.into())?;
+    out.w.flush()
+}
@@ -4,6 +4,7 @@ use std::io::{self, Read, Write, Seek, SeekFrom};
 use serde_cbor;
 use serde_json;
 
+use crate::analyz::SCHEMA_VERSION;
 use crate::lib_util::{self, CrateIndex, InternTable, EntryKind, StringId};
 
 
@@ -62,17 +63,67 @@ fn collect_roots(
     roots
 }
 
+/// Get the schema version shared by all the crates in `indexes`.  Entries in different schema
+/// versions can't be mixed, since consumers would misread some of them, so this is an error if
+/// the crates disagree.
+fn common_schema(indexes: &[CrateIndex]) -> io::Result<u32> {
+    let schema = indexes.first().map_or(SCHEMA_VERSION, |index| index.schema.unwrap_or(1));
+    for index in indexes {
+        let s = index.schema.unwrap_or(1);
+        if s != schema {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                "can't link crates emitted in different schema versions ({} and {})",
+                schema, s)));
+        }
+    }
+    Ok(schema)
+}
+
+/// Get the panic strategy shared by all the crates in `indexes`.  Whether cleanup blocks and
+/// unwind edges are present depends on the strategy, so a consumer can only model panics
+/// correctly if every crate was built the same way.  Crates that don't record a strategy are
+/// ignored.
+fn common_panic_strategy(indexes: &[CrateIndex]) -> io::Result<Option<&str>> {
+    let mut strategy = None;
+    for s in indexes.iter().filter_map(|index| index.panic_strategy.as_deref()) {
+        match strategy {
+            Some(prev) if prev != s => {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                    "can't link crates built with different panic strategies ({} and {})",
+                    prev, s)));
+            },
+            _ => strategy = Some(s),
+        }
+    }
+    Ok(strategy)
+}
+
 
 /// Combine the contents of `ocs`, producing a combined JSON crate data object as the result.
 pub fn link_crates<R, W>(inputs: &mut [R], mut output: W) -> serde_cbor::Result<()>
 where R: Read + Seek, W: Write {
     let (indexes, json_offsets) = read_crates(inputs)?;
+    // Check that the crates can be combined before writing anything.
+    let schema = common_schema(&indexes)?;
+    let panic_strategy = common_panic_strategy(&indexes)?;
     let (it, defs, translate) = assign_global_ids(&indexes);
     let roots = collect_roots(&indexes, &translate);
 
 
     let mut seen_names = HashSet::new();
     let mut worklist = roots.clone();
+    // Trait aliases are expanded away before monomorphization, so nothing refers to them by name.
+    // We keep all of them.
+    for (crate_num, index) in indexes.iter().enumerate() {
+        for (&local_id, data) in &index.items {
+            if data.locations.contains_key(&EntryKind::TraitAlias) {
+                let id = translate[&(crate_num, local_id)];
+                if seen_names.insert(id) {
+                    worklist.push(id);
+                }
+            }
+        }
+    }
     while let Some(id) = worklist.pop() {
         // Look for deps in all crates.  It seems like different sets of entries for an item can
         // appear in different crates, though I'm not sure why.
@@ -145,6 +196,28 @@ where R: Read + Seek, W: Write {
             .map_err(|e| -> io::Error { e.into() })?;
     }
     write!(output, "]")?;
+    // All crates in the program are built for the same target, so any crate's description will
+    // do.
+    write!(output, ",")?;
+    write!(output, "\"target\":")?;
+    let target = indexes.iter().find_map(|index| index.target.as_ref());
+    serde_json::to_writer(&mut output, &target)
+        .map_err(|e| -> io::Error { e.into() })?;
+    // Only the crate that produces the final artifact (normally the top-level crate) selects an
+    // allocator.
+    write!(output, ",")?;
+    write!(output, "\"allocator\":")?;
+    let allocator = indexes.iter().find_map(|index| index.allocator.as_ref());
+    serde_json::to_writer(&mut output, &allocator)
+        .map_err(|e| -> io::Error { e.into() })?;
+    write!(output, ",")?;
+    write!(output, "\"panic_strategy\":")?;
+    serde_json::to_writer(&mut output, &panic_strategy)
+        .map_err(|e| -> io::Error { e.into() })?;
+    write!(output, ",")?;
+    write!(output, "\"schema\":")?;
+    serde_json::to_writer(&mut output, &schema)
+        .map_err(|e| -> io::Error { e.into() })?;
     write!(output, "}}")?;
 
     Ok(())